@@ -0,0 +1,283 @@
+//! Adding foods by scanning a product barcode with the device camera.
+//!
+//! The camera frames are decoded in wasm with `rxing`, and the code is then
+//! looked up against a nutrition API (Open Food Facts by default). Scanning is
+//! off until the user enables it in the settings, since it asks for camera
+//! permission.
+
+use crate::calc::{Basis, IngredientPayload, format_input_value};
+use leptos::prelude::*;
+use rxing::common::HybridBinarizer;
+use rxing::{
+    BarcodeFormat, BinaryBitmap, DecodeHintType, DecodeHintValue, DecodingHintDictionary,
+    Luma8LuminanceSource, MultiFormatReader, Reader,
+};
+use std::collections::HashSet;
+use std::time::Duration;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    CanvasRenderingContext2d, HtmlCanvasElement, MediaStream, MediaStreamConstraints, window,
+};
+
+/// How often a camera frame is handed to the decoder.
+const SCAN_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Frames wider than this are scaled down before decoding; product barcodes
+/// stay readable and decoding stays fast on phones.
+const SCAN_WIDTH: u32 = 640;
+
+/// Decodes an EAN-13, EAN-8, UPC-A or UPC-E barcode from a grayscale image,
+/// one byte per pixel.
+pub fn decode_luma(luma: Vec<u8>, width: u32, height: u32) -> Option<String> {
+    let mut hints = DecodingHintDictionary::new();
+    hints.insert(
+        DecodeHintType::POSSIBLE_FORMATS,
+        DecodeHintValue::PossibleFormats(HashSet::from([
+            BarcodeFormat::EAN_13,
+            BarcodeFormat::EAN_8,
+            BarcodeFormat::UPC_A,
+            BarcodeFormat::UPC_E,
+        ])),
+    );
+    hints.insert(DecodeHintType::TRY_HARDER, DecodeHintValue::TryHarder(true));
+    let mut image = BinaryBitmap::new(HybridBinarizer::new(Luma8LuminanceSource::new(
+        luma, width, height,
+    )));
+    let result = MultiFormatReader::default()
+        .decode_with_hints(&mut image, &hints)
+        .ok()?;
+    Some(result.getText().to_string())
+}
+
+/// Converts canvas RGBA pixels to the grayscale the decoder expects.
+fn rgba_to_luma(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4)
+        .map(|pixel| {
+            let (r, g, b) = (pixel[0] as u32, pixel[1] as u32, pixel[2] as u32);
+            ((r * 299 + g * 587 + b * 114) / 1000) as u8
+        })
+        .collect()
+}
+
+/// Reads a number that nutrition APIs may send either as a JSON number or as
+/// a string.
+fn json_number(value: Option<&serde_json::Value>) -> Option<f64> {
+    let value = value?;
+    value
+        .as_f64()
+        .or_else(|| value.as_str()?.trim().parse().ok())
+        .filter(|number: &f64| number.is_finite() && *number >= 0.0)
+}
+
+/// Builds a per-100 g ingredient from an Open Food Facts style product
+/// response. Returns `None` when the product is unknown or lacks protein or
+/// fat figures.
+pub fn product_payload(json: &serde_json::Value, code: &str) -> Option<IngredientPayload> {
+    let product = json.get("product")?;
+    let nutriments = product.get("nutriments")?;
+    let protein = json_number(nutriments.get("proteins_100g"))?;
+    let fat = json_number(nutriments.get("fat_100g"))?;
+    let carbs = json_number(nutriments.get("carbohydrates_100g"));
+    let fiber = json_number(nutriments.get("fiber_100g"));
+    let sat_fat = json_number(nutriments.get("saturated-fat_100g"));
+    let sugar_alcohols = json_number(nutriments.get("polyols_100g"));
+    let label_value = |value: Option<f64>| value.map(format_input_value).unwrap_or_default();
+    let name = product
+        .get("product_name")
+        .and_then(|name| name.as_str())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map_or_else(|| format!("Barcode {code}"), str::to_string);
+    Some(IngredientPayload {
+        id: 0,
+        name,
+        protein: format_input_value(protein),
+        fat: format_input_value(fat),
+        net_carbs: format_input_value((carbs.unwrap_or(0.0) - fiber.unwrap_or(0.0)).max(0.0)),
+        sat_fat: label_value(sat_fat),
+        total_carbs: label_value(carbs),
+        fiber: label_value(fiber),
+        sugar_alcohols: label_value(sugar_alcohols),
+        servings: "100".to_string(),
+        base_amount: String::new(),
+        unit: String::new(),
+        servings_per_container: String::new(),
+        by_container: false,
+        basis: Basis::Per100g,
+        note: String::new(),
+        locked: false,
+    })
+}
+
+/// Looks a barcode up against `endpoint`, where `{code}` in the URL is
+/// replaced with the barcode. Any network, HTTP or format problem yields
+/// `None` so the caller can simply say nothing was found.
+pub async fn lookup_barcode(code: &str, endpoint: &str) -> Option<IngredientPayload> {
+    let url = endpoint.replace("{code}", code);
+    let response = gloo_net::http::Request::get(&url).send().await.ok()?;
+    if !response.ok() {
+        return None;
+    }
+    let json = response.json::<serde_json::Value>().await.ok()?;
+    product_payload(&json, code)
+}
+
+/// Asks for the rear camera and plays it in `video`.
+async fn start_camera(video: &web_sys::HtmlVideoElement) -> Result<MediaStream, JsValue> {
+    let devices = window()
+        .ok_or_else(|| JsValue::from_str("no window"))?
+        .navigator()
+        .media_devices()?;
+    let video_constraints = js_sys::Object::new();
+    js_sys::Reflect::set(
+        &video_constraints,
+        &"facingMode".into(),
+        &"environment".into(),
+    )?;
+    let constraints = MediaStreamConstraints::new();
+    constraints.set_audio(&JsValue::FALSE);
+    constraints.set_video(&video_constraints);
+    let stream: MediaStream =
+        JsFuture::from(devices.get_user_media_with_constraints(&constraints)?)
+            .await?
+            .dyn_into()?;
+    video.set_src_object(Some(&stream));
+    let _ = video.play();
+    Ok(stream)
+}
+
+fn stop_camera(stream: &MediaStream) {
+    for track in stream.get_tracks() {
+        if let Ok(track) = track.dyn_into::<web_sys::MediaStreamTrack>() {
+            track.stop();
+        }
+    }
+}
+
+/// Grabs the current video frame and tries to decode a barcode from it.
+fn scan_frame(video: &web_sys::HtmlVideoElement, canvas: &HtmlCanvasElement) -> Option<String> {
+    // HAVE_CURRENT_DATA: there is a frame to draw.
+    if video.ready_state() < 2 || video.video_width() == 0 {
+        return None;
+    }
+    let scale = (SCAN_WIDTH as f64 / video.video_width() as f64).min(1.0);
+    let width = (video.video_width() as f64 * scale).round() as u32;
+    let height = (video.video_height() as f64 * scale).round() as u32;
+    canvas.set_width(width);
+    canvas.set_height(height);
+    let context = canvas
+        .get_context("2d")
+        .ok()??
+        .dyn_into::<CanvasRenderingContext2d>()
+        .ok()?;
+    context
+        .draw_image_with_html_video_element_and_dw_and_dh(
+            video,
+            0.0,
+            0.0,
+            width as f64,
+            height as f64,
+        )
+        .ok()?;
+    let pixels = context
+        .get_image_data(0.0, 0.0, width as f64, height as f64)
+        .ok()?
+        .data();
+    decode_luma(rgba_to_luma(&pixels), width, height)
+}
+
+/// A live camera preview that calls `on_code` once with the first barcode it
+/// reads, then releases the camera. `on_close` is called when the user
+/// cancels.
+#[component]
+pub fn BarcodeScanner(on_code: Callback<String>, on_close: Callback<()>) -> impl IntoView {
+    let video_ref = NodeRef::<leptos::html::Video>::new();
+    let stream = StoredValue::new_local(None::<MediaStream>);
+    let interval = StoredValue::new_local(None::<IntervalHandle>);
+    let error = RwSignal::new(None::<String>);
+
+    let stop = move || {
+        if let Some(handle) = interval.try_update_value(Option::take).flatten() {
+            handle.clear();
+        }
+        if let Some(stream) = stream.try_update_value(Option::take).flatten() {
+            stop_camera(&stream);
+        }
+    };
+
+    Effect::new(move || {
+        let Some(video) = video_ref.get() else {
+            return;
+        };
+        leptos::task::spawn_local(async move {
+            match start_camera(&video).await {
+                Ok(camera) => {
+                    // The scanner may have closed while the permission prompt
+                    // was open; its cleanup has already run, so stop here.
+                    if let Some(Some(camera)) = stream.try_set_value(Some(camera)) {
+                        stop_camera(&camera);
+                        return;
+                    }
+                }
+                Err(_) => {
+                    error.try_set(Some(
+                        "Couldn't open the camera. Check that this site may use it.".to_string(),
+                    ));
+                    return;
+                }
+            }
+            let Some(canvas) = window()
+                .and_then(|window| window.document())
+                .and_then(|document| document.create_element("canvas").ok())
+                .and_then(|canvas| canvas.dyn_into::<HtmlCanvasElement>().ok())
+            else {
+                return;
+            };
+            let handle = set_interval_with_handle(
+                move || {
+                    if let Some(code) = scan_frame(&video, &canvas) {
+                        stop();
+                        on_code.run(code);
+                    }
+                },
+                SCAN_INTERVAL,
+            );
+            if let Ok(handle) = handle
+                && let Some(Some(handle)) = interval.try_set_value(Some(handle))
+            {
+                handle.clear();
+            }
+        });
+    });
+
+    on_cleanup(stop);
+
+    view! {
+        <div class="barcode-scanner">
+            <video
+                class="barcode-scanner__video"
+                node_ref=video_ref
+                autoplay=true
+                playsinline=true
+                prop:muted=true
+            ></video>
+            <p class="barcode-scanner__hint">
+                {move || {
+                    error
+                        .get()
+                        .unwrap_or_else(|| "Point the camera at the product's barcode.".to_string())
+                }}
+            </p>
+            <button
+                class="secondary"
+                on:click=move |_| {
+                    stop();
+                    on_close.run(());
+                }
+            >
+                "Cancel"
+            </button>
+        </div>
+    }
+}
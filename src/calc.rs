@@ -0,0 +1,1325 @@
+//! Recipe math, number formatting, and the share-link encoding.
+//!
+//! Nothing in here touches the DOM, so these helpers can be reused outside
+//! the Leptos component, e.g. by scripts that generate share links.
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// The recipe stored in a share link.
+///
+/// Share links have the form `<base>#recipe=<data>`, where `<data>` is this
+/// struct serialized as JSON and then encoded as URL-safe base64 without
+/// padding:
+///
+/// ```json
+/// {
+///   "name": "High-protein chili",
+///   "ingredients": [
+///     { "id": 0, "name": "Lean beef", "protein": "21", "fat": "5.5", "net_carbs": "", "servings": "4" }
+///   ]
+/// }
+/// ```
+///
+/// `name` and `notes` may be `null` or omitted, as may `ui`, the author's
+/// display preferences (see [`UiPrefs`]). Quantities are stored as the
+/// text the user typed so values like "3.333" survive a round trip exactly;
+/// JSON numbers (used by older links) are accepted too. Quantities must be
+/// non-negative numbers, fractions like "3/4", or empty; anything else is
+/// treated as zero when the link is opened.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecipePayload {
+    pub name: Option<String>,
+    /// Free-text notes printed with the recipe; they never affect the math.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    pub ingredients: Vec<IngredientPayload>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ui: Option<UiPrefs>,
+    /// Opens the link read-only, as with `?mode=view`, unless the recipient
+    /// asks to edit with `?mode=edit` or makes a copy.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+}
+
+/// Display preferences that travel with a recipe, so a shared link opens with
+/// the presentation its author chose, e.g. `{ "rounding": "ceil", "ratio":
+/// "pf" }`. Missing entries fall back to the viewer's own settings, and the
+/// viewer can still change them locally.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UiPrefs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rounding: Option<RoundMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ratio: Option<RatioMode>,
+}
+
+/// A recipe kept in this browser's library.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedRecipe {
+    pub recipe: RecipePayload,
+    /// When it was last saved, in milliseconds since the Unix epoch. Each save
+    /// is later than every earlier one, even if the clock goes backwards.
+    pub modified_at: f64,
+    /// Starred; favorites are listed before the rest.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
+}
+
+impl SavedRecipe {
+    /// The name the library lists it under.
+    pub fn name(&self) -> &str {
+        match self.recipe.name.as_deref().map(str::trim) {
+            Some(name) if !name.is_empty() => name,
+            _ => "Untitled recipe",
+        }
+    }
+}
+
+/// Saves `recipe` into `library` at time `now`, replacing any entry with the
+/// same name (ignoring case) but keeping its star, and keeps the library
+/// sorted with [`sort_library`].
+pub fn save_to_library(library: &mut Vec<SavedRecipe>, recipe: RecipePayload, now: f64) {
+    let latest = library
+        .iter()
+        .map(|saved| saved.modified_at + 1.0)
+        .fold(now, f64::max);
+    let mut saved = SavedRecipe {
+        recipe,
+        modified_at: latest,
+        favorite: false,
+    };
+    library.retain(|existing| {
+        let same = existing.name().eq_ignore_ascii_case(saved.name());
+        saved.favorite |= same && existing.favorite;
+        !same
+    });
+    library.push(saved);
+    sort_library(library);
+}
+
+/// Adds a backup's recipes to `library`. A recipe whose name is already taken
+/// is added as "Name (2)", "Name (3)" and so on rather than replacing it.
+/// Returns how many were renamed.
+pub fn merge_library(library: &mut Vec<SavedRecipe>, imported: Vec<SavedRecipe>) -> usize {
+    let mut renamed = 0;
+    for mut saved in imported {
+        let taken = |name: &str, library: &[SavedRecipe]| {
+            library
+                .iter()
+                .any(|existing| existing.name().eq_ignore_ascii_case(name))
+        };
+        if taken(saved.name(), library) {
+            let base = saved.name().to_string();
+            let name = (2..)
+                .map(|copy| format!("{base} ({copy})"))
+                .find(|name| !taken(name, library))
+                .unwrap_or(base);
+            saved.recipe.name = Some(name);
+            renamed += 1;
+        }
+        library.push(saved);
+    }
+    sort_library(library);
+    renamed
+}
+
+/// Orders the library favorites first, then most recently saved first.
+pub fn sort_library(library: &mut [SavedRecipe]) {
+    library.sort_by(|a, b| {
+        b.favorite
+            .cmp(&a.favorite)
+            .then(b.modified_at.total_cmp(&a.modified_at))
+    });
+}
+
+/// One ingredient of a [`RecipePayload`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IngredientPayload {
+    /// Identifier unique within the recipe.
+    pub id: usize,
+    pub name: String,
+    /// Grams of protein per basis unit.
+    #[serde(deserialize_with = "deserialize_quantity")]
+    pub protein: String,
+    /// Grams of fat per basis unit.
+    #[serde(deserialize_with = "deserialize_quantity")]
+    pub fat: String,
+    /// Grams of net carbs (carbs minus fiber) per basis unit.
+    #[serde(deserialize_with = "deserialize_quantity")]
+    pub net_carbs: String,
+    /// Grams of saturated fat per basis unit, tracked alongside total fat.
+    /// Optional; it never changes the P:E ratio.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_quantity",
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub sat_fat: String,
+    /// Optional label breakdown of the carbs per basis unit. When
+    /// `total_carbs` is set, `net_carbs` was worked out from these with
+    /// [`net_carbs_from`].
+    #[serde(
+        default,
+        deserialize_with = "deserialize_quantity",
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub total_carbs: String,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_quantity",
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub fiber: String,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_quantity",
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub sugar_alcohols: String,
+    /// Amount used in the recipe: servings, or grams for the gram bases. With
+    /// a `base_amount` this is the amount used in the label's own unit.
+    #[serde(deserialize_with = "deserialize_quantity")]
+    pub servings: String,
+    /// Optional serving size a per-serving label states its macros for, in
+    /// `unit`, e.g. 3 for "per 3 crackers". See [`amount_multiplier`].
+    #[serde(
+        default,
+        deserialize_with = "deserialize_quantity",
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub base_amount: String,
+    /// Free-text unit of `base_amount` and the amount used, e.g. "crackers".
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub unit: String,
+    /// Optional "servings per container" from a per-serving label.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_quantity",
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub servings_per_container: String,
+    /// `servings` counts whole containers rather than servings. See
+    /// [`container_servings`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub by_container: bool,
+    /// What the macros are given per. Omitted for the default, per serving.
+    #[serde(default, skip_serializing_if = "Basis::is_per_serving")]
+    pub basis: Basis,
+    /// Free-text note such as "drained weight"; never affects the math.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    /// Keeps `servings` fixed when the batch tools rescale the recipe.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+}
+
+/// Reads a quantity written either as a string or, for links created before
+/// quantities were stored as text, as a JSON number.
+fn deserialize_quantity<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawQuantity {
+        Number(f64),
+        Text(String),
+    }
+
+    Ok(match RawQuantity::deserialize(deserializer)? {
+        RawQuantity::Number(value) => format_input_value(value),
+        RawQuantity::Text(text) => canonical_quantity(&text),
+    })
+}
+
+/// What an ingredient's macros are measured against, and therefore what its
+/// "servings" field counts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Basis {
+    /// Macros per label serving; the amount is a number of servings.
+    #[default]
+    PerServing,
+    /// Macros per 100 g; the amount is grams used.
+    #[serde(rename = "per_100g")]
+    Per100g,
+    /// Macros per gram; the amount is grams used.
+    PerGram,
+}
+
+impl Basis {
+    pub const ALL: [Basis; 3] = [Basis::PerServing, Basis::Per100g, Basis::PerGram];
+
+    pub fn is_per_serving(&self) -> bool {
+        *self == Basis::PerServing
+    }
+
+    /// Factor applied to the per-basis macros for `amount` used.
+    pub fn multiplier(self, amount: f64) -> f64 {
+        match self {
+            Basis::PerServing | Basis::PerGram => amount,
+            Basis::Per100g => amount / 100.0,
+        }
+    }
+
+    /// Suffix for macro labels, e.g. "per 100 g".
+    pub fn macro_label(self) -> &'static str {
+        match self {
+            Basis::PerServing => "per serving",
+            Basis::Per100g => "per 100 g",
+            Basis::PerGram => "per gram",
+        }
+    }
+
+    /// Label for the amount field.
+    pub fn amount_label(self) -> &'static str {
+        match self {
+            Basis::PerServing => "Servings used in recipe",
+            Basis::Per100g | Basis::PerGram => "Grams used in recipe",
+        }
+    }
+
+    /// Weight in grams the macros are stated for, when the basis implies one.
+    /// Per-serving labels don't say how much a serving weighs.
+    pub fn reference_grams(self) -> Option<f64> {
+        match self {
+            Basis::PerServing => None,
+            Basis::Per100g => Some(100.0),
+            Basis::PerGram => Some(1.0),
+        }
+    }
+
+    /// Unit suffix for a displayed amount, e.g. " g".
+    pub fn amount_unit(self) -> &'static str {
+        match self {
+            Basis::PerServing => "",
+            Basis::Per100g | Basis::PerGram => " g",
+        }
+    }
+}
+
+/// The label's own serving size, when a per-serving label states it as an
+/// amount of some unit ("per 3 crackers"). `None` for the gram bases and when
+/// no base amount was entered, in which case the amount is plain servings.
+pub fn custom_base(basis: Basis, base_amount: &str) -> Option<f64> {
+    let base = parse_quantity(base_amount);
+    (basis.is_per_serving() && base > 0.0).then_some(base)
+}
+
+/// Servings in one container, when a per-serving amount is counted in whole
+/// containers. `None` unless `by_container` is set on a per-serving label
+/// with a positive `servings_per_container`.
+pub fn container_servings(
+    basis: Basis,
+    by_container: bool,
+    servings_per_container: &str,
+) -> Option<f64> {
+    let servings = parse_quantity(servings_per_container);
+    (basis.is_per_serving() && by_container && servings > 0.0).then_some(servings)
+}
+
+/// Factor applied to the per-basis macros for `amount` used. With a custom
+/// base the amount is in the label's unit, so 5 crackers of a "per 3
+/// crackers" label counts as 5/3 of a serving. With `per_container` (see
+/// [`container_servings`]) the amount is a number of containers instead.
+pub fn amount_multiplier(
+    basis: Basis,
+    amount: f64,
+    base_amount: &str,
+    per_container: Option<f64>,
+) -> f64 {
+    if let Some(servings) = per_container {
+        return amount * servings;
+    }
+    match custom_base(basis, base_amount) {
+        Some(base) => amount / base,
+        None => basis.multiplier(amount),
+    }
+}
+
+/// [`Basis::macro_label`], or e.g. "per 3 crackers" with a custom base.
+pub fn custom_macro_label(basis: Basis, base_amount: &str, unit: &str) -> String {
+    match custom_base(basis, base_amount) {
+        Some(_) => format!("per {} {}", base_amount.trim(), unit.trim())
+            .trim_end()
+            .to_string(),
+        None => basis.macro_label().to_string(),
+    }
+}
+
+/// [`Basis::amount_label`], naming the label's unit with a custom base.
+pub fn custom_amount_label(basis: Basis, base_amount: &str, unit: &str) -> String {
+    match custom_base(basis, base_amount) {
+        Some(_) if !unit.trim().is_empty() => {
+            format!("Amount used in recipe ({})", unit.trim())
+        }
+        Some(_) => "Amount used in recipe".to_string(),
+        None => basis.amount_label().to_string(),
+    }
+}
+
+/// [`Basis::amount_unit`], or e.g. " crackers" with a custom base.
+pub fn custom_amount_unit(basis: Basis, base_amount: &str, unit: &str) -> String {
+    match custom_base(basis, base_amount) {
+        Some(_) if !unit.trim().is_empty() => format!(" {}", unit.trim()),
+        _ => basis.amount_unit().to_string(),
+    }
+}
+
+impl fmt::Display for Basis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Basis::PerServing => "per_serving",
+            Basis::Per100g => "per_100g",
+            Basis::PerGram => "per_gram",
+        })
+    }
+}
+
+impl FromStr for Basis {
+    type Err = ();
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        Basis::ALL
+            .into_iter()
+            .find(|basis| basis.to_string() == raw)
+            .ok_or(())
+    }
+}
+
+/// Serializes `payload` into the `<data>` part of a share link.
+pub fn encode_payload(payload: &RecipePayload) -> String {
+    // Serializing plain structs with string keys cannot fail.
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(payload).unwrap_or_default())
+}
+
+pub fn decode_recipe(encoded: &str) -> Option<RecipePayload> {
+    try_decode_recipe(encoded).ok()
+}
+
+/// Like [`decode_recipe`], but says why a payload was rejected, e.g.
+/// "missing field `ingredients` at line 1 column 20".
+pub fn try_decode_recipe(encoded: &str) -> Result<RecipePayload, String> {
+    let raw = URL_SAFE_NO_PAD
+        .decode(encoded.as_bytes())
+        .map_err(|err| format!("not valid URL-safe base64: {err}"))?;
+    serde_json::from_slice(&raw).map_err(|err| err.to_string())
+}
+
+/// Builds a complete share link for `payload`, e.g.
+/// `build_share_url("https://pedietcalc.snoyman.com/", &payload)`.
+pub fn build_share_url(base: &str, payload: &RecipePayload) -> String {
+    format!("{base}#recipe={}", encode_payload(payload))
+}
+
+/// Extracts the recipe from a share link. Accepts a full URL, a bare
+/// `#recipe=...` hash, or just `recipe=...`. Other fragment entries such as
+/// `#top&recipe=...` are ignored.
+pub fn parse_share_url(url: &str) -> Option<RecipePayload> {
+    let fragment = url.split_once('#').map_or(url, |(_, fragment)| fragment);
+    decode_recipe(Fragment::parse(fragment.trim()).get("recipe")?)
+}
+
+/// A URL fragment made of `&`-separated `key=value` entries, e.g.
+/// `#top&recipe=...`. Entries without a `=` (plain anchors) are kept as bare
+/// keys, and serializing preserves the original order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fragment {
+    entries: Vec<(String, Option<String>)>,
+}
+
+impl Fragment {
+    /// Parses a fragment with or without the leading `#`.
+    pub fn parse(raw: &str) -> Self {
+        let entries = raw
+            .trim_start_matches('#')
+            .split('&')
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split_once('=') {
+                Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                None => (entry.to_string(), None),
+            })
+            .collect();
+        Fragment { entries }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(name, _)| name == key)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Replaces the first entry named `key`, or appends one if missing.
+    pub fn set(&mut self, key: &str, value: impl Into<String>) {
+        let value = Some(value.into());
+        match self.entries.iter().position(|(name, _)| name == key) {
+            Some(index) => {
+                self.entries[index].1 = value;
+                let mut seen = 0;
+                self.entries.retain(|(name, _)| {
+                    if name == key {
+                        seen += 1;
+                        seen == 1
+                    } else {
+                        true
+                    }
+                });
+            }
+            None => self.entries.push((key.to_string(), value)),
+        }
+    }
+}
+
+/// Serializes without the leading `#`.
+impl fmt::Display for Fragment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (key, value)) in self.entries.iter().enumerate() {
+            if index > 0 {
+                f.write_str("&")?;
+            }
+            f.write_str(key)?;
+            if let Some(value) = value {
+                write!(f, "={value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_quantity(raw: &str) -> f64 {
+    sanitize_quantity(parse_amount(raw).unwrap_or(0.0))
+}
+
+/// Reads a plain number or a sum of fractions and mixed numbers such as
+/// "3/4", "1 1/2" or "1/2 + 1/4".
+fn parse_amount(raw: &str) -> Option<f64> {
+    let raw = raw.trim();
+    if let Ok(value) = raw.parse::<f64>() {
+        return Some(value);
+    }
+    let mut total = 0.0;
+    for term in raw.split('+') {
+        let mut parts = term.split_whitespace().peekable();
+        parts.peek()?;
+        for part in parts {
+            total += match part.split_once('/') {
+                Some((numer, denom)) => {
+                    let denom = denom.parse::<f64>().ok().filter(|denom| *denom != 0.0)?;
+                    numer.parse::<f64>().ok()? / denom
+                }
+                None => part.parse::<f64>().ok()?,
+            };
+        }
+    }
+    total.is_finite().then_some(total)
+}
+
+/// Whether `raw` is a valid amount written with fractions, which is kept as
+/// typed rather than rewritten as a decimal.
+pub fn is_fraction(raw: &str) -> bool {
+    raw.contains('/') && parse_amount(raw).is_some_and(|value| value >= 0.0)
+}
+
+/// Percentage of the calories that come from protein, or `None` when there
+/// are no calories.
+pub fn protein_calorie_share(totals: (f64, f64, f64)) -> Option<f64> {
+    let (protein, _, _) = macro_calories(totals);
+    percent_of(protein, total_calories(totals))
+}
+
+/// Grams of protein per 100 kcal, or `None` when there are no calories.
+pub fn protein_density(protein_g: f64, total_kcal: f64) -> Option<f64> {
+    (total_kcal > f64::MIN_POSITIVE).then(|| protein_g / total_kcal * 100.0)
+}
+
+/// `total_macro` grams scaled to 100 g of a recipe weighing `total_weight`
+/// grams, or `None` when the weight is unknown.
+pub fn per_100g(total_macro: f64, total_weight: f64) -> Option<f64> {
+    (total_weight > f64::MIN_POSITIVE).then(|| total_macro / total_weight * 100.0)
+}
+
+/// Net carbs from a nutrition label: total carbs minus fiber, minus the part
+/// of the sugar alcohols that doesn't count. `sugar_alcohol_factor` is the
+/// share of sugar alcohols counted as carbs (0, ½ or 1). Never negative.
+pub fn net_carbs_from(
+    total_carbs: f64,
+    fiber: f64,
+    sugar_alcohols: f64,
+    sugar_alcohol_factor: f64,
+) -> f64 {
+    let factor = sugar_alcohol_factor.clamp(0.0, 1.0);
+    (total_carbs - fiber - sugar_alcohols * (1.0 - factor)).max(0.0)
+}
+
+/// Grams of a recipe that aren't macros (water, ash and so on): the total
+/// weight minus the protein, fat, net carbs and fiber in it. Never negative.
+pub fn other_mass(total_weight: f64, macro_grams: f64) -> f64 {
+    (total_weight - macro_grams).max(0.0)
+}
+
+/// True when the macros add up to more grams than the weight they are stated
+/// for, e.g. 60 g protein + 50 g fat "per 100 g", which points at a typo.
+pub fn macros_exceed_weight(basis: Basis, protein: f64, fat: f64, net_carbs: f64) -> bool {
+    basis
+        .reference_grams()
+        .is_some_and(|grams| protein + fat + net_carbs > grams + 1e-9)
+}
+
+/// Finds non-negative amounts for each ingredient so the recipe's
+/// (protein, fat, net carbs) come as close as possible to `targets`, in the
+/// least-squares sense. `per_unit` holds each ingredient's macros per unit of
+/// its amount field. Macros whose target is `None` are left unconstrained.
+///
+/// This is a plain projected coordinate descent (non-negative least
+/// squares), so it has some limitations:
+///
+/// * Every gram counts equally, so a large protein target dominates a small
+///   carb target.
+/// * With more than three ingredients there are usually many exact answers;
+///   the one returned depends on the ingredient order.
+/// * Targets that cannot be met exactly (say, fat without any fatty
+///   ingredient) are only approximated.
+///
+/// Returns `None` when no target is set or no ingredient contributes to a
+/// targeted macro.
+pub fn solve_recipe(targets: [Option<f64>; 3], per_unit: &[[f64; 3]]) -> Option<Vec<f64>> {
+    let rows = (0..3)
+        .filter_map(|row| targets[row].map(|target| (row, target)))
+        .collect::<Vec<_>>();
+    let column_norms = per_unit
+        .iter()
+        .map(|column| {
+            rows.iter()
+                .map(|&(row, _)| column[row] * column[row])
+                .sum::<f64>()
+        })
+        .collect::<Vec<f64>>();
+    if rows.is_empty() || column_norms.iter().all(|norm| *norm <= f64::MIN_POSITIVE) {
+        return None;
+    }
+
+    let mut amounts = vec![0.0; per_unit.len()];
+    // Residual (target minus current total) for each targeted macro.
+    let mut residual = rows.iter().map(|&(_, target)| target).collect::<Vec<f64>>();
+    for _ in 0..1_000 {
+        let mut largest_step = 0.0_f64;
+        for (index, column) in per_unit.iter().enumerate() {
+            if column_norms[index] <= f64::MIN_POSITIVE {
+                continue;
+            }
+            let gradient = rows
+                .iter()
+                .zip(&residual)
+                .map(|(&(row, _), r)| column[row] * r)
+                .sum::<f64>();
+            let updated = (amounts[index] + gradient / column_norms[index]).max(0.0);
+            let step = updated - amounts[index];
+            if step != 0.0 {
+                for ((row, _), r) in rows.iter().zip(residual.iter_mut()) {
+                    *r -= column[*row] * step;
+                }
+                amounts[index] = updated;
+                largest_step = largest_step.max(step.abs());
+            }
+        }
+        if largest_step < 1e-9 {
+            break;
+        }
+    }
+    Some(amounts)
+}
+
+/// Strips everything but digits, fraction syntax ("1 1/2", "1/2 + 1/4") and
+/// one decimal separator per number from typed or pasted text, turning a
+/// decimal comma into a point: "1,5 g" becomes "1.5 ".
+pub fn filter_numeric_input(raw: &str) -> String {
+    let mut seen_separator = false;
+    raw.chars()
+        .filter_map(|c| match c {
+            '0'..='9' => Some(c),
+            '.' | ',' if !seen_separator => {
+                seen_separator = true;
+                Some('.')
+            }
+            '/' | '+' | ' ' => {
+                seen_separator = false;
+                Some(c)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Adds `delta` to a quantity field (as the arrow keys do), clamping at zero
+/// and returning the value formatted for the input.
+pub fn step_quantity(raw: &str, delta: f64) -> String {
+    format_input_value(sanitize_quantity(parse_quantity(raw) + delta))
+}
+
+pub fn sanitize_quantity(value: f64) -> f64 {
+    if value.is_finite() {
+        value.max(0.0)
+    } else {
+        0.0
+    }
+}
+
+/// How displayed numbers are rounded to two decimals. Calculations always
+/// use full precision; this only affects presentation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundMode {
+    #[default]
+    Nearest,
+    Floor,
+    Ceil,
+}
+
+impl RoundMode {
+    pub const ALL: [RoundMode; 3] = [RoundMode::Nearest, RoundMode::Floor, RoundMode::Ceil];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RoundMode::Nearest => "Round to nearest",
+            RoundMode::Floor => "Round down",
+            RoundMode::Ceil => "Round up",
+        }
+    }
+}
+
+impl fmt::Display for RoundMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RoundMode::Nearest => "nearest",
+            RoundMode::Floor => "floor",
+            RoundMode::Ceil => "ceil",
+        })
+    }
+}
+
+impl FromStr for RoundMode {
+    type Err = ();
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "nearest" => Ok(RoundMode::Nearest),
+            "floor" => Ok(RoundMode::Floor),
+            "ceil" => Ok(RoundMode::Ceil),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Rough P:E quality bands used to mark ratios without relying on color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeBand {
+    /// P:E of 2 or more.
+    High,
+    /// P:E from 1 up to 2.
+    Moderate,
+    /// P:E below 1.
+    Low,
+}
+
+impl PeBand {
+    pub const ALL: [PeBand; 3] = [PeBand::High, PeBand::Moderate, PeBand::Low];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            PeBand::High => "●",
+            PeBand::Moderate => "◐",
+            PeBand::Low => "○",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PeBand::High => "P:E 2 or more: high protein",
+            PeBand::Moderate => "P:E 1 to 2: moderate",
+            PeBand::Low => "P:E below 1: energy-dense",
+        }
+    }
+}
+
+/// A rough "is this keto?" reading of a recipe, shown as a badge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DietClass {
+    KetoFriendly,
+    Moderate,
+    HighCarb,
+}
+
+impl DietClass {
+    pub fn label(self) -> &'static str {
+        match self {
+            DietClass::KetoFriendly => "Keto-friendly",
+            DietClass::Moderate => "Moderate",
+            DietClass::HighCarb => "High-carb",
+        }
+    }
+}
+
+/// Net-carb limits per portion used by [`classify_recipe`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DietThresholds {
+    /// At or below this many grams per portion counts as keto-friendly.
+    pub keto_max_carbs: f64,
+    /// Above this many grams per portion counts as high-carb.
+    pub high_carb_min: f64,
+}
+
+/// Classifies a recipe split into `portions` by its net carbs per portion.
+/// Carbs making up most of the energy (more net carbs than fat) also rule
+/// out keto-friendly. `None` for an empty recipe or no portions.
+pub fn classify_recipe(
+    totals: (f64, f64, f64),
+    portions: f64,
+    thresholds: DietThresholds,
+) -> Option<DietClass> {
+    let (protein, fat, carbs) = totals;
+    if portions <= 0.0 || protein + fat + carbs <= f64::MIN_POSITIVE {
+        return None;
+    }
+    let per_portion = carbs / portions;
+    Some(if per_portion > thresholds.high_carb_min {
+        DietClass::HighCarb
+    } else if per_portion <= thresholds.keto_max_carbs && carbs <= fat.max(protein) {
+        DietClass::KetoFriendly
+    } else {
+        DietClass::Moderate
+    })
+}
+
+/// The band for `totals`, or `None` when there is no energy to compare.
+pub fn pe_band(totals: (f64, f64, f64)) -> Option<PeBand> {
+    energy_ratio(totals).map(|ratio| {
+        if ratio >= 2.0 {
+            PeBand::High
+        } else if ratio >= 1.0 {
+            PeBand::Moderate
+        } else {
+            PeBand::Low
+        }
+    })
+}
+
+/// Which ratio is shown for ingredients and totals: protein against energy
+/// (fat + net carbs), against fat alone, or against net carbs alone.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RatioMode {
+    #[default]
+    #[serde(rename = "pe")]
+    Energy,
+    #[serde(rename = "pf")]
+    Fat,
+    #[serde(rename = "pc")]
+    Carbs,
+}
+
+impl RatioMode {
+    pub const ALL: [RatioMode; 3] = [RatioMode::Energy, RatioMode::Fat, RatioMode::Carbs];
+
+    /// Short name, e.g. "P:E".
+    pub fn label(self) -> &'static str {
+        match self {
+            RatioMode::Energy => "P:E",
+            RatioMode::Fat => "P:F",
+            RatioMode::Carbs => "P:C",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            RatioMode::Energy => "P:E — protein ÷ (fat + net carbs)",
+            RatioMode::Fat => "P:F — protein ÷ fat",
+            RatioMode::Carbs => "P:C — protein ÷ net carbs",
+        }
+    }
+
+    /// Grams the protein is divided by.
+    pub fn denominator(self, (_, fat, net_carbs): (f64, f64, f64)) -> f64 {
+        match self {
+            RatioMode::Energy => fat + net_carbs,
+            RatioMode::Fat => fat,
+            RatioMode::Carbs => net_carbs,
+        }
+    }
+}
+
+impl fmt::Display for RatioMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RatioMode::Energy => "pe",
+            RatioMode::Fat => "pf",
+            RatioMode::Carbs => "pc",
+        })
+    }
+}
+
+impl FromStr for RatioMode {
+    type Err = ();
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "pe" => Ok(RatioMode::Energy),
+            "pf" => Ok(RatioMode::Fat),
+            "pc" => Ok(RatioMode::Carbs),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Rounds `value` to two decimals using `mode`.
+pub fn round_hundredths(value: f64, mode: RoundMode) -> f64 {
+    round_to_step(value, 100.0, mode)
+}
+
+/// Rounds `value` to a multiple of `1 / scale` using `mode`.
+fn round_to_step(value: f64, scale: f64, mode: RoundMode) -> f64 {
+    // Snap away float noise first so that e.g. 0.1 * 3 (0.30000000000000004)
+    // is not rounded up to 0.31.
+    let scaled = (value * scale * 1e6).round() / 1e6;
+    let rounded = match mode {
+        RoundMode::Nearest => scaled.round(),
+        RoundMode::Floor => scaled.floor(),
+        RoundMode::Ceil => scaled.ceil(),
+    };
+    rounded / scale
+}
+
+/// Separators for displayed numbers, e.g. "1,234.56" or "1.234,56".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub group: char,
+    pub decimal: char,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            group: ',',
+            decimal: '.',
+        }
+    }
+}
+
+static NUMBER_FORMAT: OnceLock<NumberFormat> = OnceLock::new();
+
+/// Sets the separators [`format_number`] uses, once at startup (e.g. from the
+/// browser's locale). Later calls are ignored.
+pub fn set_number_format(format: NumberFormat) {
+    let _ = NUMBER_FORMAT.set(format);
+}
+
+/// The separators in use: those set with [`set_number_format`], or "1,234.56".
+pub fn number_format() -> NumberFormat {
+    NUMBER_FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Formats `value` to two decimals with thousands grouping, in the
+/// separators set with [`set_number_format`].
+pub fn format_number(value: f64, mode: RoundMode) -> String {
+    format_number_with(value, mode, number_format())
+}
+
+/// [`format_number`] with explicit separators. A real amount too small to
+/// show at two decimals reads "<0.01" rather than collapsing to "0.00".
+pub fn format_number_with(value: f64, mode: RoundMode, format: NumberFormat) -> String {
+    let rounded = round_hundredths(value, mode);
+    // Also catches -0.0, so tiny negative noise never shows as "-0.00".
+    if rounded == 0.0 {
+        // The same float-noise snap as the rounding, so 1e-12 is still zero.
+        let meaningful = (value * 100.0 * 1e6).round() > 0.0;
+        let zero = group_digits("0.00", format);
+        return if meaningful {
+            format!("<{}", group_digits("0.01", format))
+        } else {
+            zero
+        };
+    }
+    let digits = format!("{:.2}", rounded.abs());
+    let sign = if rounded < 0.0 { "-" } else { "" };
+    format!("{sign}{}", group_digits(&digits, format))
+}
+
+/// Rewrites plain "1234567.89" with `format`'s separators.
+fn group_digits(plain: &str, format: NumberFormat) -> String {
+    let (whole, fraction) = plain.split_once('.').unwrap_or((plain, ""));
+    let mut out = String::new();
+    for (index, digit) in whole.chars().enumerate() {
+        if index > 0 && (whole.len() - index) % 3 == 0 {
+            out.push(format.group);
+        }
+        out.push(digit);
+    }
+    if !fraction.is_empty() {
+        out.push(format.decimal);
+        out.push_str(fraction);
+    }
+    out
+}
+
+/// Formats an energy amount as whole kilocalories, e.g. "312 kcal".
+pub fn format_kcal(value: f64, mode: RoundMode) -> String {
+    // Adding 0.0 turns -0.0 into 0.0.
+    format!("{:.0} kcal", round_to_step(value, 1.0, mode) + 0.0)
+}
+
+/// How long ago `then` was at `now` (both in milliseconds), e.g. "2h ago".
+pub fn humanize_since(now: f64, then: f64) -> String {
+    let minutes = ((now - then) / 60_000.0).floor();
+    if minutes.is_nan() || minutes < 1.0 {
+        "just now".to_string()
+    } else if minutes < 60.0 {
+        format!("{minutes}m ago")
+    } else if minutes < 24.0 * 60.0 {
+        format!("{}h ago", (minutes / 60.0).floor())
+    } else {
+        format!("{}d ago", (minutes / (24.0 * 60.0)).floor())
+    }
+}
+
+/// Returns `total` as a percentage of `target`, or `None` when no positive
+/// target is set.
+pub fn percent_of(total: f64, target: f64) -> Option<f64> {
+    if target > 0.0 {
+        Some(total / target * 100.0)
+    } else {
+        None
+    }
+}
+
+/// `totals` with `fiber` grams counted as carbs, for those who include fiber
+/// in the energy term of the P:E ratio.
+pub fn with_fiber(totals: (f64, f64, f64), fiber: f64) -> (f64, f64, f64) {
+    (totals.0, totals.1, totals.2 + fiber)
+}
+
+/// Formats `numer / denom`, or "—" when there is nothing to divide by or
+/// either side isn't a finite number.
+pub fn ratio(numer: f64, denom: f64, mode: RoundMode) -> String {
+    if numer.is_finite() && denom.is_finite() && denom > f64::MIN_POSITIVE {
+        format_number(numer / denom, mode)
+    } else {
+        "—".to_string()
+    }
+}
+
+/// Formats the ratio selected by `kind` for `(protein, fat, net_carbs)`. A
+/// ratio with protein but nothing to divide by is unbounded rather than
+/// missing, and shows `unbounded` (e.g. "∞", or "—" to treat it like an empty
+/// recipe); a recipe with no protein either always shows "—".
+pub fn format_ratio_as(
+    kind: RatioMode,
+    totals: (f64, f64, f64),
+    mode: RoundMode,
+    unbounded: &str,
+) -> String {
+    let denom = kind.denominator(totals);
+    if totals.0 > 0.0 && totals.0.is_finite() && (0.0..=f64::MIN_POSITIVE).contains(&denom) {
+        unbounded.to_string()
+    } else {
+        ratio(totals.0, denom, mode)
+    }
+}
+
+/// How the ratio settings show a ratio: which one, whether fiber counts as
+/// energy, and what an unbounded ratio reads as.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RatioStyle {
+    pub kind: RatioMode,
+    pub include_fiber: bool,
+    pub unbounded: String,
+}
+
+impl Default for RatioStyle {
+    fn default() -> Self {
+        Self {
+            kind: RatioMode::Energy,
+            include_fiber: false,
+            unbounded: "—".to_string(),
+        }
+    }
+}
+
+impl RatioStyle {
+    fn totals(&self, totals: (f64, f64, f64), fiber: f64) -> (f64, f64, f64) {
+        with_fiber(totals, if self.include_fiber { fiber } else { 0.0 })
+    }
+
+    /// The ratio for `totals` plus `fiber` grams, formatted for display.
+    pub fn format(&self, totals: (f64, f64, f64), fiber: f64, mode: RoundMode) -> String {
+        format_ratio_as(self.kind, self.totals(totals, fiber), mode, &self.unbounded)
+    }
+
+    /// The ratio as a number, when there is something to divide by.
+    pub fn value(&self, totals: (f64, f64, f64), fiber: f64) -> Option<f64> {
+        let totals = self.totals(totals, fiber);
+        let denom = self.kind.denominator(totals);
+        let ratio = totals.0 / denom;
+        (denom > f64::MIN_POSITIVE && ratio.is_finite()).then_some(ratio)
+    }
+}
+
+/// Rounds to the nearest quarter, e.g. 1.3 becomes 1.25.
+pub fn snap_to_quarter(value: f64) -> f64 {
+    (value * 4.0).round() / 4.0
+}
+
+/// Atwater factors: kilocalories per gram of each macro.
+pub const KCAL_PER_GRAM_PROTEIN: f64 = 4.0;
+pub const KCAL_PER_GRAM_FAT: f64 = 9.0;
+pub const KCAL_PER_GRAM_NET_CARBS: f64 = 4.0;
+
+/// Kilocalories contributed by protein, fat, and net carbs respectively.
+pub fn macro_calories(totals: (f64, f64, f64)) -> (f64, f64, f64) {
+    (
+        totals.0 * KCAL_PER_GRAM_PROTEIN,
+        totals.1 * KCAL_PER_GRAM_FAT,
+        totals.2 * KCAL_PER_GRAM_NET_CARBS,
+    )
+}
+
+/// Total kilocalories from protein, fat, and net carbs.
+pub fn total_calories(totals: (f64, f64, f64)) -> f64 {
+    let (protein, fat, carbs) = macro_calories(totals);
+    protein + fat + carbs
+}
+
+/// Protein ÷ (fat + net carbs), or `None` when there is no energy.
+pub fn energy_ratio(totals: (f64, f64, f64)) -> Option<f64> {
+    let energy = totals.1 + totals.2;
+    (energy > f64::MIN_POSITIVE).then(|| totals.0 / energy)
+}
+
+/// Formats a signed difference, e.g. "+1.50" or "-0.25".
+pub fn format_delta(value: f64, mode: RoundMode) -> String {
+    let formatted = format_number(value, mode);
+    if formatted.starts_with('-') || formatted == format_number(0.0, mode) {
+        formatted
+    } else {
+        format!("+{formatted}")
+    }
+}
+
+/// Rewrites a raw field value into the form the calculator actually uses,
+/// e.g. "-3" becomes empty and ".5" becomes "0.50".
+pub fn normalize_quantity_input(raw: &str) -> String {
+    if is_fraction(raw) {
+        raw.trim().to_string()
+    } else {
+        format_input_value(parse_quantity(raw))
+    }
+}
+
+/// The text stored in share links for a quantity field: the trimmed input
+/// when it is a valid quantity, so no precision is lost, and otherwise its
+/// normalized form.
+pub fn canonical_quantity(raw: &str) -> String {
+    let trimmed = raw.trim();
+    match trimmed.parse::<f64>() {
+        Ok(value) if value.is_finite() && value >= 0.0 => trimmed.to_string(),
+        _ if is_fraction(trimmed) => trimmed.to_string(),
+        _ => normalize_quantity_input(trimmed),
+    }
+}
+
+/// Formats a quantity for an input field: empty for zero, two decimals when
+/// that is exact, and otherwise every significant digit so that values such
+/// as 3.333 are not silently rounded.
+pub fn format_input_value(value: f64) -> String {
+    // Drop float noise such as 0.1 + 0.2 = 0.30000000000000004.
+    let value = (sanitize_quantity(value) * 1e9).round() / 1e9;
+    if value == 0.0 {
+        String::new()
+    } else if (value * 100.0).round() / 100.0 == value {
+        format!("{value:.2}")
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_ratio(totals: (f64, f64, f64), mode: RoundMode) -> String {
+        RatioStyle::default().format(totals, 0.0, mode)
+    }
+
+    #[test]
+    fn ratio_divides_protein_by_energy() {
+        assert_eq!(format_ratio((30.0, 10.0, 5.0), RoundMode::Nearest), "2.00");
+        assert_eq!(format_ratio((10.0, 3.0, 0.0), RoundMode::Nearest), "3.33");
+        assert_eq!(format_ratio((10.0, 3.0, 0.0), RoundMode::Ceil), "3.34");
+        assert_eq!(format_ratio((0.0, 4.0, 1.0), RoundMode::Nearest), "0.00");
+    }
+
+    #[test]
+    fn ratio_is_a_dash_without_energy() {
+        assert_eq!(format_ratio((0.0, 0.0, 0.0), RoundMode::Nearest), "—");
+        assert_eq!(format_ratio((5.0, -2.0, 1.0), RoundMode::Nearest), "—");
+        // Subnormal leftovers from float noise count as no energy.
+        assert_eq!(format_ratio((5.0, 1e-310, 0.0), RoundMode::Nearest), "—");
+    }
+
+    #[test]
+    fn protein_only_ratio_is_a_dash() {
+        assert_eq!(format_ratio((25.0, 0.0, 0.0), RoundMode::Nearest), "—");
+    }
+
+    #[test]
+    fn protein_only_ratio_can_show_a_symbol() {
+        let shown = |totals| format_ratio_as(RatioMode::Energy, totals, RoundMode::Nearest, "∞");
+        assert_eq!(shown((25.0, 0.0, 0.0)), "∞");
+        assert_eq!(shown((0.0, 0.0, 0.0)), "—");
+        assert_eq!(shown((30.0, 10.0, 5.0)), "2.00");
+    }
+
+    #[test]
+    fn tiny_energy_still_gives_a_ratio() {
+        assert_eq!(
+            format_ratio((1.0, 0.001, 0.0), RoundMode::Nearest),
+            "1,000.00"
+        );
+    }
+
+    const EUROPEAN: NumberFormat = NumberFormat {
+        group: '.',
+        decimal: ',',
+    };
+
+    #[test]
+    fn large_numbers_are_grouped() {
+        let us = NumberFormat::default();
+        assert_eq!(
+            format_number_with(1234567.891, RoundMode::Nearest, us),
+            "1,234,567.89"
+        );
+        assert_eq!(
+            format_number_with(1234567.891, RoundMode::Nearest, EUROPEAN),
+            "1.234.567,89"
+        );
+        assert_eq!(
+            format_number_with(999.999, RoundMode::Nearest, us),
+            "1,000.00"
+        );
+        assert_eq!(format_number_with(123.4, RoundMode::Nearest, us), "123.40");
+        assert_eq!(
+            format_number_with(-2500.0, RoundMode::Nearest, us),
+            "-2,500.00"
+        );
+    }
+
+    #[test]
+    fn small_amounts_are_not_shown_as_zero() {
+        let us = NumberFormat::default();
+        assert_eq!(format_number_with(0.004, RoundMode::Nearest, us), "<0.01");
+        assert_eq!(format_number_with(0.004, RoundMode::Floor, us), "<0.01");
+        assert_eq!(format_number_with(0.004, RoundMode::Ceil, us), "0.01");
+        assert_eq!(
+            format_number_with(0.004, RoundMode::Nearest, EUROPEAN),
+            "<0,01"
+        );
+        // Float noise and negatives still read as zero.
+        assert_eq!(format_number_with(1e-12, RoundMode::Nearest, us), "0.00");
+        assert_eq!(format_number_with(-0.004, RoundMode::Nearest, us), "0.00");
+        assert_eq!(format_number_with(0.0, RoundMode::Nearest, us), "0.00");
+    }
+
+    #[test]
+    fn non_finite_totals_are_a_dash() {
+        for totals in [
+            (f64::NAN, 1.0, 1.0),
+            (1.0, f64::NAN, 0.0),
+            (f64::INFINITY, 1.0, 0.0),
+            (1.0, f64::INFINITY, 0.0),
+            (1.0, 0.0, f64::NEG_INFINITY),
+        ] {
+            assert_eq!(format_ratio(totals, RoundMode::Nearest), "—", "{totals:?}");
+        }
+    }
+
+    #[test]
+    fn relative_times() {
+        let now = 1_700_000_000_000.0;
+        assert_eq!(humanize_since(now, now - 30_000.0), "just now");
+        assert_eq!(humanize_since(now, now + 5_000.0), "just now");
+        assert_eq!(humanize_since(now, now - 5.0 * 60_000.0), "5m ago");
+        assert_eq!(humanize_since(now, now - 2.5 * 3_600_000.0), "2h ago");
+        assert_eq!(humanize_since(now, now - 3.0 * 86_400_000.0), "3d ago");
+    }
+
+    fn recipe(name: &str) -> RecipePayload {
+        RecipePayload {
+            name: Some(name.to_string()),
+            notes: None,
+            ingredients: Vec::new(),
+            ui: None,
+            locked: false,
+        }
+    }
+
+    #[test]
+    fn saving_replaces_by_name_and_sorts_recent_first() {
+        let mut library = Vec::new();
+        save_to_library(&mut library, recipe("Chili"), 1_000.0);
+        save_to_library(&mut library, recipe("Stew"), 2_000.0);
+        // The clock went backwards; the timestamp still moves forward.
+        save_to_library(&mut library, recipe(" chili "), 1_500.0);
+        let names = library.iter().map(SavedRecipe::name).collect::<Vec<_>>();
+        assert_eq!(names, ["chili", "Stew"]);
+        assert_eq!(library[0].modified_at, 2_001.0);
+    }
+
+    #[test]
+    fn favorites_sort_first_and_survive_a_resave() {
+        let mut library = Vec::new();
+        save_to_library(&mut library, recipe("Chili"), 1_000.0);
+        save_to_library(&mut library, recipe("Stew"), 2_000.0);
+        library[1].favorite = true;
+        sort_library(&mut library);
+        save_to_library(&mut library, recipe("Soup"), 3_000.0);
+        save_to_library(&mut library, recipe("Chili"), 4_000.0);
+        let names = library.iter().map(SavedRecipe::name).collect::<Vec<_>>();
+        assert_eq!(names, ["Chili", "Soup", "Stew"]);
+        assert!(library[0].favorite);
+    }
+
+    #[test]
+    fn merging_a_backup_renames_clashing_names() {
+        let mut library = Vec::new();
+        save_to_library(&mut library, recipe("Chili"), 1_000.0);
+        save_to_library(&mut library, recipe("Chili (2)"), 2_000.0);
+        let backup = vec![
+            SavedRecipe {
+                recipe: recipe("chili"),
+                modified_at: 500.0,
+                favorite: false,
+            },
+            SavedRecipe {
+                recipe: recipe("Stew"),
+                modified_at: 3_000.0,
+                favorite: false,
+            },
+        ];
+        assert_eq!(merge_library(&mut library, backup), 1);
+        let names = library.iter().map(SavedRecipe::name).collect::<Vec<_>>();
+        assert_eq!(names, ["Stew", "Chili (2)", "Chili", "chili (3)"]);
+    }
+
+    #[test]
+    fn numeric_filter_keeps_fractions() {
+        assert_eq!(filter_numeric_input("1,5 g"), "1.5 ");
+        assert_eq!(filter_numeric_input("1 1/2"), "1 1/2");
+        assert_eq!(filter_numeric_input("1/2 + 1/4 cup"), "1/2 + 1/4 ");
+        assert_eq!(filter_numeric_input("1.5.2"), "1.52");
+        assert_eq!(parse_quantity(&filter_numeric_input("1 1/2 tbsp")), 1.5);
+    }
+}
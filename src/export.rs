@@ -0,0 +1,237 @@
+//! Human-readable text versions of a recipe, for pasting into forums, notes
+//! apps and messages.
+//!
+//! Like `calc`, nothing in here touches the DOM.
+
+use crate::calc::{
+    Basis, IngredientPayload, RatioStyle, RoundMode, amount_multiplier, container_servings,
+    custom_base, custom_macro_label, format_number, number_format, parse_quantity,
+};
+
+/// Escapes the characters that would break a Markdown table cell.
+fn markdown_cell(text: &str) -> String {
+    text.trim().replace('\\', "\\\\").replace('|', "\\|")
+}
+
+fn amount(ingredient: &IngredientPayload) -> String {
+    let servings = if ingredient.servings.trim().is_empty() {
+        "0"
+    } else {
+        ingredient.servings.trim()
+    };
+    let per_container = container_servings(
+        ingredient.basis,
+        ingredient.by_container,
+        &ingredient.servings_per_container,
+    );
+    if per_container.is_some() {
+        return format!("{servings} containers");
+    }
+    if custom_base(ingredient.basis, &ingredient.base_amount).is_some() {
+        return format!("{servings} {}", ingredient.unit.trim())
+            .trim_end()
+            .to_string();
+    }
+    match ingredient.basis {
+        Basis::PerServing if servings == "1" => "1 serving".to_string(),
+        Basis::PerServing => format!("{servings} servings"),
+        basis => format!("{servings}{}", basis.amount_unit()),
+    }
+}
+
+/// A Markdown document with the recipe name as a heading and a table of its
+/// ingredients: the label macros, the grams they add to the recipe, and each
+/// row's ratio in `style`, followed by a totals row. Blank cards are left out.
+/// `fiber` is the recipe's fiber in grams, counted if `style` includes it.
+pub fn to_markdown(
+    ingredients: &[IngredientPayload],
+    name: &str,
+    totals: (f64, f64, f64),
+    fiber: f64,
+    mode: RoundMode,
+    style: &RatioStyle,
+) -> String {
+    let name = name.trim();
+    let mut out = format!("# {}\n\n", if name.is_empty() { "Recipe" } else { name });
+    out.push_str(&format!(
+        "| Ingredient | Amount | Macros | Protein (g) | Fat (g) | Net carbs (g) \
+         | Protein in recipe (g) | Fat in recipe (g) | Net carbs in recipe (g) | {} |\n",
+        style.kind.label(),
+    ));
+    out.push_str("| --- | --- | --- | ---: | ---: | ---: | ---: | ---: | ---: | ---: |\n");
+    for ingredient in ingredients {
+        let label = [&ingredient.protein, &ingredient.fat, &ingredient.net_carbs]
+            .map(|raw| parse_quantity(raw));
+        if ingredient.name.trim().is_empty() && label.iter().all(|value| *value == 0.0) {
+            continue;
+        }
+        let multiplier = amount_multiplier(
+            ingredient.basis,
+            parse_quantity(&ingredient.servings),
+            &ingredient.base_amount,
+            container_servings(
+                ingredient.basis,
+                ingredient.by_container,
+                &ingredient.servings_per_container,
+            ),
+        );
+        let in_recipe = (
+            label[0] * multiplier,
+            label[1] * multiplier,
+            label[2] * multiplier,
+        );
+        let name = if ingredient.name.trim().is_empty() {
+            "Unnamed ingredient".to_string()
+        } else {
+            markdown_cell(&ingredient.name)
+        };
+        out.push_str(&format!(
+            "| {name} | {} | {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            amount(ingredient),
+            custom_macro_label(ingredient.basis, &ingredient.base_amount, &ingredient.unit),
+            format_number(label[0], mode),
+            format_number(label[1], mode),
+            format_number(label[2], mode),
+            format_number(in_recipe.0, mode),
+            format_number(in_recipe.1, mode),
+            format_number(in_recipe.2, mode),
+            style.format(
+                in_recipe,
+                parse_quantity(&ingredient.fiber) * multiplier,
+                mode
+            ),
+        ));
+    }
+    out.push_str(&format!(
+        "| **Total** | | | | | | **{}** | **{}** | **{}** | **{}** |\n",
+        format_number(totals.0, mode),
+        format_number(totals.1, mode),
+        format_number(totals.2, mode),
+        style.format(totals, fiber, mode),
+    ));
+    out
+}
+
+/// A one-line summary for texting, e.g. "High-protein chili: 120g protein,
+/// 40g fat, 15g net carbs, P:E 2.18", with the ratio in `style`.
+pub fn to_plain_summary(
+    name: &str,
+    totals: (f64, f64, f64),
+    fiber: f64,
+    mode: RoundMode,
+    style: &RatioStyle,
+) -> String {
+    // "120" rather than "120.00", which reads better in a message.
+    let grams = |value: f64| {
+        let number = format_number(value, mode);
+        number
+            .trim_end_matches('0')
+            .trim_end_matches(number_format().decimal)
+            .to_string()
+    };
+    let name = name.trim();
+    format!(
+        "{}: {}g protein, {}g fat, {}g net carbs, {} {}",
+        if name.is_empty() { "Recipe" } else { name },
+        grams(totals.0),
+        grams(totals.1),
+        grams(totals.2),
+        style.kind.label(),
+        style.format(totals, fiber, mode),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::RatioMode;
+
+    fn ingredient(json: &str) -> IngredientPayload {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn summary_trims_trailing_zeros() {
+        assert_eq!(
+            to_plain_summary(
+                " Chili ",
+                (1234.5, 40.0, 15.5),
+                0.0,
+                RoundMode::Nearest,
+                &RatioStyle::default()
+            ),
+            "Chili: 1,234.5g protein, 40g fat, 15.5g net carbs, P:E 22.24"
+        );
+        assert_eq!(
+            to_plain_summary(
+                "",
+                (0.0, 0.0, 0.0),
+                0.0,
+                RoundMode::Nearest,
+                &RatioStyle::default()
+            ),
+            "Recipe: 0g protein, 0g fat, 0g net carbs, P:E —"
+        );
+    }
+
+    #[test]
+    fn summary_uses_the_selected_ratio() {
+        let style = RatioStyle {
+            kind: RatioMode::Fat,
+            include_fiber: true,
+            unbounded: "∞".to_string(),
+        };
+        assert_eq!(
+            to_plain_summary("Shake", (30.0, 0.0, 5.0), 3.0, RoundMode::Nearest, &style),
+            "Shake: 30g protein, 0g fat, 5g net carbs, P:F ∞"
+        );
+    }
+
+    #[test]
+    fn markdown_lists_rows_and_totals() {
+        let ingredients = [
+            ingredient(
+                r#"{"id": 0, "name": "Eggs | large", "protein": 6, "fat": 5, "net_carbs": 0.5,
+                    "servings": 2, "basis": "per_serving"}"#,
+            ),
+            ingredient(
+                r#"{"id": 1, "name": "", "protein": "", "fat": "", "net_carbs": "",
+                    "servings": 1, "basis": "per_serving"}"#,
+            ),
+            ingredient(
+                r#"{"id": 2, "name": "Chicken", "protein": 20, "fat": 10, "net_carbs": 0,
+                    "fiber": 5, "servings": 150, "basis": "per_100g"}"#,
+            ),
+        ];
+        let style = RatioStyle {
+            kind: RatioMode::Carbs,
+            include_fiber: true,
+            ..RatioStyle::default()
+        };
+        let markdown = to_markdown(
+            &ingredients,
+            "",
+            (42.0, 25.0, 1.0),
+            7.5,
+            RoundMode::Nearest,
+            &style,
+        );
+        let lines = markdown.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "# Recipe");
+        assert!(lines[2].ends_with("| P:C |"));
+        // The blank card is left out.
+        assert_eq!(lines.len(), 7);
+        assert_eq!(
+            lines[4],
+            "| Eggs \\| large | 2.00 servings | per serving | 6.00 | 5.00 | 0.50 | 12.00 | 10.00 | 1.00 | 12.00 |"
+        );
+        assert_eq!(
+            lines[5],
+            "| Chicken | 150.00 g | per 100 g | 20.00 | 10.00 | 0.00 | 30.00 | 15.00 | 0.00 | 4.00 |"
+        );
+        assert_eq!(
+            lines[6],
+            "| **Total** | | | | | | **42.00** | **25.00** | **1.00** | **4.94** |"
+        );
+    }
+}
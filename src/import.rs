@@ -0,0 +1,553 @@
+//! Importing ingredients from CSV exports of other nutrition apps, such as
+//! MyFitnessPal, Cronometer and FatSecret.
+//!
+//! Like `calc`, nothing in here touches the DOM.
+
+use crate::calc::{Basis, IngredientPayload, format_input_value, parse_quantity};
+
+/// Column positions of the fields we understand in a CSV header row.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FieldMap {
+    pub name: usize,
+    pub protein: usize,
+    pub fat: usize,
+    /// Total carbohydrates; net carbs are this minus `fiber`.
+    pub carbs: Option<usize>,
+    pub fiber: Option<usize>,
+    /// A column that already holds net carbs.
+    pub net_carbs: Option<usize>,
+    pub servings: Option<usize>,
+    pub net_carb_rule: NetCarbRule,
+}
+
+/// How an export's carb columns become our net carbs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NetCarbRule {
+    /// Use a net carbs column when there is one, otherwise total carbs minus
+    /// fiber.
+    #[default]
+    NetColumnFirst,
+    /// Always total carbs minus fiber, for apps whose own net carbs figure
+    /// may also subtract other things (such as sugar alcohols).
+    TotalMinusFiber,
+}
+
+/// The header names one app uses in its export. Names are compared after
+/// [`normalize_header`], so "Protein (g)" is listed as "protein".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImportProfile {
+    pub app: &'static str,
+    /// Headers that together identify this app's export. Empty for the
+    /// generic profile, which is used when no other profile matches.
+    pub markers: &'static [&'static str],
+    pub name: &'static [&'static str],
+    pub protein: &'static [&'static str],
+    pub fat: &'static [&'static str],
+    pub carbs: &'static [&'static str],
+    pub fiber: &'static [&'static str],
+    pub net_carbs: &'static [&'static str],
+    pub servings: &'static [&'static str],
+    pub net_carb_rule: NetCarbRule,
+}
+
+/// MyFitnessPal exports and hand-made spreadsheets.
+pub const GENERIC_PROFILE: ImportProfile = ImportProfile {
+    app: "MyFitnessPal or spreadsheet",
+    markers: &[],
+    name: &["food", "food name", "name", "description", "item"],
+    protein: &["protein"],
+    fat: &["fat", "total fat"],
+    carbs: &[
+        "carbohydrates",
+        "carbohydrate",
+        "carbs",
+        "total carbohydrate",
+    ],
+    fiber: &["fiber", "fibre", "dietary fiber"],
+    net_carbs: &["net carbs", "net carbohydrates"],
+    servings: &["servings", "serving", "quantity"],
+    net_carb_rule: NetCarbRule::NetColumnFirst,
+};
+
+/// Cronometer's "Food & Recipe Entries" export. Its "Amount" column is text
+/// such as "1.00 serving - 100 g", so servings aren't read from it, and its
+/// "Net Carbs" follow the user's own Cronometer settings.
+pub const CRONOMETER_PROFILE: ImportProfile = ImportProfile {
+    app: "Cronometer",
+    markers: &["food name", "energy", "net carbs"],
+    name: &["food name"],
+    protein: &["protein"],
+    fat: &["fat"],
+    carbs: &["carbs"],
+    fiber: &["fiber"],
+    net_carbs: &["net carbs"],
+    servings: &[],
+    net_carb_rule: NetCarbRule::TotalMinusFiber,
+};
+
+/// FatSecret's food diary export, with abbreviated headers such as
+/// "Cals (kcal)" and "Prot (g)".
+pub const FATSECRET_PROFILE: ImportProfile = ImportProfile {
+    app: "FatSecret",
+    markers: &["cals", "prot"],
+    name: &["food", "description", "food name"],
+    protein: &["prot", "protein"],
+    fat: &["fat"],
+    carbs: &["carbs", "carbh", "carbohydrate"],
+    fiber: &["fiber", "fibre"],
+    net_carbs: &[],
+    servings: &["qty", "servings"],
+    net_carb_rule: NetCarbRule::NetColumnFirst,
+};
+
+/// Profiles tried in order; the generic one goes last.
+pub const IMPORT_PROFILES: [ImportProfile; 3] =
+    [CRONOMETER_PROFILE, FATSECRET_PROFILE, GENERIC_PROFILE];
+
+/// Picks the profile whose marker headers all appear in `headers`.
+pub fn detect_profile(headers: &[String]) -> ImportProfile {
+    IMPORT_PROFILES
+        .into_iter()
+        .find(|profile| {
+            profile
+                .markers
+                .iter()
+                .all(|marker| find_column(headers, &[marker]).is_some())
+        })
+        .unwrap_or(GENERIC_PROFILE)
+}
+
+/// Lowercases a header and drops a trailing unit such as " (g)", so that
+/// "Protein (g)" and "protein" are treated alike.
+fn normalize_header(header: &str) -> String {
+    let header = header.trim().to_lowercase();
+    match header.split_once('(') {
+        Some((name, _)) => name.trim().to_string(),
+        None => header,
+    }
+}
+
+fn find_column(headers: &[String], names: &[&str]) -> Option<usize> {
+    headers
+        .iter()
+        .position(|header| names.contains(&normalize_header(header).as_str()))
+}
+
+/// Maps an export's headers ("Food", "Protein (g)", "Fat (g)",
+/// "Carbohydrates (g)", "Fiber (g)", ...) to our fields, using the
+/// [`ImportProfile`] sniffed from them. Unknown columns are ignored; a
+/// missing name, protein, fat or carbs column is an error.
+pub fn map_headers(headers: &[String]) -> Result<FieldMap, String> {
+    map_headers_with(&detect_profile(headers), headers)
+}
+
+fn map_headers_with(profile: &ImportProfile, headers: &[String]) -> Result<FieldMap, String> {
+    let required = |label: &str, names: &[&str]| {
+        find_column(headers, names).ok_or_else(|| format!("Missing a \"{label}\" column."))
+    };
+    let name = required("Food", profile.name)?;
+    let protein = required("Protein (g)", profile.protein)?;
+    let fat = required("Fat (g)", profile.fat)?;
+    let carbs = find_column(headers, profile.carbs);
+    let net_carbs = find_column(headers, profile.net_carbs);
+    let needs_total = profile.net_carb_rule == NetCarbRule::TotalMinusFiber;
+    if carbs.is_none() && (net_carbs.is_none() || needs_total) {
+        return Err("Missing a \"Carbohydrates (g)\" column.".to_string());
+    }
+    Ok(FieldMap {
+        name,
+        protein,
+        fat,
+        carbs,
+        fiber: find_column(headers, profile.fiber),
+        net_carbs,
+        servings: find_column(headers, profile.servings),
+        net_carb_rule: profile.net_carb_rule,
+    })
+}
+
+/// Splits delimited text into rows of fields, handling quoted fields with
+/// embedded delimiters, doubled quotes and line breaks.
+fn parse_rows(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => row.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|row| row.iter().any(|field| !field.trim().is_empty()));
+    rows
+}
+
+fn cell(row: &[String], index: usize) -> &str {
+    row.get(index).map_or("", |field| field.trim())
+}
+
+/// Reads ingredients from CSV text whose first row is a header. Each row
+/// becomes a per-serving ingredient; ids are left at zero for the caller to
+/// assign.
+pub fn parse_csv(text: &str) -> Result<Vec<IngredientPayload>, String> {
+    let mut rows = parse_rows(text, ',').into_iter();
+    let headers = rows.next().ok_or_else(|| "The CSV is empty.".to_string())?;
+    let map = map_headers(&headers)?;
+    let ingredients = rows
+        .map(|row| {
+            let net_column = map
+                .net_carbs
+                .filter(|_| map.net_carb_rule == NetCarbRule::NetColumnFirst);
+            let net_carbs = match net_column {
+                Some(index) => parse_quantity(cell(&row, index)),
+                None => {
+                    let carbs = map
+                        .carbs
+                        .map_or(0.0, |index| parse_quantity(cell(&row, index)));
+                    let fiber = map
+                        .fiber
+                        .map_or(0.0, |index| parse_quantity(cell(&row, index)));
+                    (carbs - fiber).max(0.0)
+                }
+            };
+            IngredientPayload {
+                id: 0,
+                name: cell(&row, map.name).to_string(),
+                protein: format_input_value(parse_quantity(cell(&row, map.protein))),
+                fat: format_input_value(parse_quantity(cell(&row, map.fat))),
+                net_carbs: format_input_value(net_carbs),
+                sat_fat: String::new(),
+                total_carbs: String::new(),
+                fiber: String::new(),
+                sugar_alcohols: String::new(),
+                servings: match map.servings.map(|index| cell(&row, index)) {
+                    Some(raw) if !raw.is_empty() => format_input_value(parse_quantity(raw)),
+                    _ => "1".to_string(),
+                },
+                base_amount: String::new(),
+                unit: String::new(),
+                servings_per_container: String::new(),
+                by_container: false,
+                basis: Basis::PerServing,
+                note: String::new(),
+                locked: false,
+            }
+        })
+        .collect::<Vec<_>>();
+    if ingredients.is_empty() {
+        return Err("The CSV has a header but no rows.".to_string());
+    }
+    Ok(ingredients)
+}
+
+/// Rows pasted from a spreadsheet, plus a message for each row that had to be
+/// skipped.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PastedTable {
+    pub ingredients: Vec<IngredientPayload>,
+    pub errors: Vec<String>,
+}
+
+/// Column order assumed when a pasted table has no recognizable header.
+const POSITIONAL_COLUMNS: FieldMap = FieldMap {
+    name: 0,
+    protein: 1,
+    fat: 2,
+    carbs: None,
+    fiber: None,
+    net_carbs: Some(3),
+    servings: Some(4),
+    net_carb_rule: NetCarbRule::NetColumnFirst,
+};
+
+/// Reads a block copied from a spreadsheet (tab-separated) or typed as CSV.
+/// A header row is used when it names the columns; otherwise the columns are
+/// name, protein, fat, net carbs and, optionally, servings. Rows with
+/// unreadable numbers are skipped and reported rather than counted as zero.
+pub fn parse_table(text: &str) -> PastedTable {
+    let first_line = text
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("");
+    let delimiter = if first_line.contains('\t') { '\t' } else { ',' };
+    let mut rows = parse_rows(text, delimiter);
+    let (map, has_header) = match rows.first().map(|headers| map_headers(headers)) {
+        Some(Ok(map)) => {
+            rows.remove(0);
+            (map, true)
+        }
+        _ => (POSITIONAL_COLUMNS, false),
+    };
+
+    let mut table = PastedTable::default();
+    for (index, row) in rows.iter().enumerate() {
+        // Rows are numbered as the user sees them, counting any header.
+        let line = index + 1 + usize::from(has_header);
+        match table_row(&map, row, delimiter) {
+            Ok(ingredient) => table.ingredients.push(ingredient),
+            Err(error) => table.errors.push(format!("Row {line}: {error}")),
+        }
+    }
+    table
+}
+
+fn table_row(map: &FieldMap, row: &[String], delimiter: char) -> Result<IngredientPayload, String> {
+    let number = |index: Option<usize>| -> Result<f64, String> {
+        let raw = index.map_or("", |index| cell(row, index));
+        if raw.is_empty() {
+            return Ok(0.0);
+        }
+        // Spreadsheets in many locales copy decimals with a comma.
+        let normalized = if delimiter == '\t' {
+            raw.replace(',', ".")
+        } else {
+            raw.to_string()
+        };
+        match normalized.parse::<f64>() {
+            Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+            _ => Err(format!("\"{raw}\" is not a number.")),
+        }
+    };
+    let net_column = map
+        .net_carbs
+        .filter(|_| map.net_carb_rule == NetCarbRule::NetColumnFirst);
+    let net_carbs = match net_column {
+        Some(index) => number(Some(index))?,
+        None => (number(map.carbs)? - number(map.fiber)?).max(0.0),
+    };
+    let servings = match map.servings.map(|index| cell(row, index)) {
+        Some(raw) if !raw.is_empty() => format_input_value(number(map.servings)?),
+        _ => "1".to_string(),
+    };
+    Ok(IngredientPayload {
+        id: 0,
+        name: cell(row, map.name).to_string(),
+        protein: format_input_value(number(Some(map.protein))?),
+        fat: format_input_value(number(Some(map.fat))?),
+        net_carbs: format_input_value(net_carbs),
+        sat_fat: String::new(),
+        total_carbs: String::new(),
+        fiber: String::new(),
+        sugar_alcohols: String::new(),
+        servings,
+        base_amount: String::new(),
+        unit: String::new(),
+        servings_per_container: String::new(),
+        by_container: false,
+        basis: Basis::PerServing,
+        note: String::new(),
+        locked: false,
+    })
+}
+
+/// Reads the grams from the rest of a label line such as " 8g 10%" or
+/// " <1 g", skipping milligram amounts.
+fn label_grams(rest: &str) -> Option<f64> {
+    let start = rest.find(|c: char| c.is_ascii_digit())?;
+    let number = &rest[start..];
+    let end = number
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(number.len());
+    number[end..]
+        .trim_start()
+        .starts_with('g')
+        .then(|| number[..end].parse().ok())
+        .flatten()
+}
+
+/// Reads a Nutrition Facts label copied as text into a per-serving
+/// ingredient. "Total Carbohydrate", "Dietary Fiber" and "Sugar Alcohol" fill
+/// the label breakdown so net carbs are worked out from them; a literal "Net
+/// Carbs" line is only used when the label has no total. Returns `None` when
+/// no macros were found.
+pub fn parse_label(text: &str) -> Option<IngredientPayload> {
+    let mut protein = None;
+    let mut fat = None;
+    let mut sat_fat = None;
+    let mut total_carbs = None;
+    let mut fiber = None;
+    let mut sugar_alcohols = None;
+    let mut net_carbs = None;
+    for line in text.lines() {
+        let line = line.trim().to_lowercase();
+        let field = if line.starts_with("saturated fat") {
+            &mut sat_fat
+        } else if line.starts_with("total fat") || line.starts_with("fat ") {
+            &mut fat
+        } else if line.starts_with("total carb") || line.starts_with("carbohydrate") {
+            &mut total_carbs
+        } else if line.starts_with("dietary fiber") || line.starts_with("fiber") {
+            &mut fiber
+        } else if line.starts_with("sugar alcohol") {
+            &mut sugar_alcohols
+        } else if line.starts_with("net carb") {
+            &mut net_carbs
+        } else if line.starts_with("protein") {
+            &mut protein
+        } else {
+            continue;
+        };
+        if field.is_none() {
+            *field = label_grams(&line);
+        }
+    }
+    if protein.is_none() && fat.is_none() && total_carbs.is_none() && net_carbs.is_none() {
+        return None;
+    }
+    let grams = |value: Option<f64>| value.map(format_input_value).unwrap_or_default();
+    let net_carbs = match total_carbs {
+        Some(total) => (total - fiber.unwrap_or(0.0) - sugar_alcohols.unwrap_or(0.0)).max(0.0),
+        None => net_carbs.unwrap_or(0.0),
+    };
+    Some(IngredientPayload {
+        id: 0,
+        name: String::new(),
+        protein: format_input_value(protein.unwrap_or(0.0)),
+        fat: format_input_value(fat.unwrap_or(0.0)),
+        net_carbs: format_input_value(net_carbs),
+        sat_fat: grams(sat_fat),
+        total_carbs: grams(total_carbs),
+        fiber: grams(total_carbs.and(fiber)),
+        sugar_alcohols: grams(total_carbs.and(sugar_alcohols)),
+        servings: "1".to_string(),
+        base_amount: String::new(),
+        unit: String::new(),
+        servings_per_container: String::new(),
+        by_container: false,
+        basis: Basis::PerServing,
+        note: String::new(),
+        locked: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const US_LABEL: &str = "\
+Nutrition Facts
+8 servings per container
+Serving size 2/3 cup (55g)
+
+Amount per serving
+Calories 230
+
+% Daily Value*
+Total Fat 8g 10%
+  Saturated Fat 1g 5%
+  Trans Fat 0g
+Cholesterol 0mg 0%
+Sodium 160mg 7%
+Total Carbohydrate 37g 13%
+  Dietary Fiber 4g 14%
+  Total Sugars 12g
+    Includes 10g Added Sugars 20%
+Protein 3g
+";
+
+    #[test]
+    fn us_label_fills_the_carb_breakdown() {
+        let ingredient = parse_label(US_LABEL).unwrap();
+        assert_eq!(ingredient.protein, "3.00");
+        assert_eq!(ingredient.fat, "8.00");
+        assert_eq!(ingredient.sat_fat, "1.00");
+        assert_eq!(ingredient.total_carbs, "37.00");
+        assert_eq!(ingredient.fiber, "4.00");
+        assert_eq!(ingredient.net_carbs, "33.00");
+        assert_eq!(ingredient.basis, Basis::PerServing);
+    }
+
+    #[test]
+    fn net_carbs_line_is_used_without_a_total() {
+        let ingredient = parse_label("Protein 20g\nFat 5 g\nNet Carbs 2g\n").unwrap();
+        assert_eq!(ingredient.net_carbs, "2.00");
+        assert_eq!(ingredient.fat, "5.00");
+        assert!(ingredient.total_carbs.is_empty());
+    }
+
+    #[test]
+    fn total_carbohydrate_wins_over_net_carbs() {
+        let label = "Total Carbohydrate 20g\nDietary Fiber 5g\nSugar Alcohol 6g\nNet Carbs 9g\n";
+        let ingredient = parse_label(label).unwrap();
+        assert_eq!(ingredient.sugar_alcohols, "6.00");
+        assert_eq!(ingredient.net_carbs, "9.00");
+    }
+
+    #[test]
+    fn text_without_macros_is_rejected() {
+        assert_eq!(parse_label("Sodium 160mg\nCalories 230"), None);
+    }
+
+    fn headers(line: &str) -> Vec<String> {
+        line.split(',').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn cronometer_export_is_recognized() {
+        let header = "Day,Group,Food Name,Amount,Energy (kcal),Carbs (g),Fiber (g),Net Carbs (g),Fat (g),Protein (g)";
+        assert_eq!(detect_profile(&headers(header)).app, "Cronometer");
+        // Its own net carbs (here also minus sugar alcohols) are ignored.
+        let csv = format!("{header}\n2024-01-02,Lunch,Greek Yogurt,1.00 cup,150,12,2,6,4,20\n");
+        let ingredients = parse_csv(&csv).unwrap();
+        assert_eq!(ingredients[0].name, "Greek Yogurt");
+        assert_eq!(ingredients[0].protein, "20.00");
+        assert_eq!(ingredients[0].net_carbs, "10.00");
+        assert_eq!(ingredients[0].servings, "1");
+    }
+
+    #[test]
+    fn fatsecret_export_is_recognized() {
+        let header = "Date,Meal,Food,Cals (kcal),Fat (g),Carbs (g),Prot (g),Fiber (g),Sugar (g)";
+        assert_eq!(detect_profile(&headers(header)).app, "FatSecret");
+        let csv = format!("{header}\n2024-01-02,Dinner,Chicken Breast,165,3.6,0,31,0,0\n");
+        let ingredients = parse_csv(&csv).unwrap();
+        assert_eq!(ingredients[0].name, "Chicken Breast");
+        assert_eq!(ingredients[0].protein, "31.00");
+        assert_eq!(ingredients[0].fat, "3.60");
+    }
+
+    #[test]
+    fn myfitnesspal_export_uses_the_generic_profile() {
+        let header = "Food,Calories,Carbohydrates (g),Fat (g),Protein (g),Fiber (g)";
+        assert_eq!(detect_profile(&headers(header)), GENERIC_PROFILE);
+        let ingredients = parse_csv(&format!("{header}\nOats,150,27,3,5,4\n")).unwrap();
+        assert_eq!(ingredients[0].net_carbs, "23.00");
+    }
+
+    #[test]
+    fn pasted_table_with_positional_header_counts_the_header_row() {
+        let table = parse_table(
+            "Name\tProtein\tFat\tNet carbs\tServings\nEggs\t6\t5\t0,5\t2\nOil\tx\t14\t0\t1\n",
+        );
+        assert_eq!(table.ingredients.len(), 1);
+        assert_eq!(table.ingredients[0].name, "Eggs");
+        assert_eq!(table.ingredients[0].net_carbs, "0.50");
+        assert_eq!(table.ingredients[0].servings, "2.00");
+        assert_eq!(table.errors, ["Row 3: \"x\" is not a number."]);
+    }
+
+    #[test]
+    fn pasted_table_without_header_uses_column_order() {
+        let table = parse_table("Eggs,6,5,0.5\nOil,0,14,oops\n");
+        assert_eq!(table.ingredients.len(), 1);
+        assert_eq!(table.ingredients[0].protein, "6.00");
+        assert_eq!(table.ingredients[0].servings, "1");
+        assert_eq!(table.errors, ["Row 2: \"oops\" is not a number."]);
+    }
+}
@@ -1,10 +1,70 @@
-use base64::Engine;
-use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+mod barcode;
+mod calc;
+mod export;
+mod import;
+
+use barcode::{BarcodeScanner, lookup_barcode};
+use calc::{
+    Basis, DietThresholds, Fragment, IngredientPayload, KCAL_PER_GRAM_FAT, KCAL_PER_GRAM_NET_CARBS,
+    KCAL_PER_GRAM_PROTEIN, NumberFormat, PeBand, RatioMode, RatioStyle, RecipePayload, RoundMode,
+    SavedRecipe, UiPrefs, amount_multiplier, build_share_url, canonical_quantity, classify_recipe,
+    container_servings, custom_amount_label, custom_amount_unit, custom_base, custom_macro_label,
+    decode_recipe, encode_payload, filter_numeric_input, format_delta, format_input_value,
+    format_kcal, format_number, format_ratio_as, humanize_since, is_fraction, macro_calories,
+    macros_exceed_weight, merge_library, net_carbs_from, normalize_quantity_input, other_mass,
+    parse_quantity, parse_share_url, pe_band, per_100g, percent_of, protein_calorie_share,
+    protein_density, round_hundredths, save_to_library, set_number_format, snap_to_quarter,
+    solve_recipe, sort_library, step_quantity, total_calories, try_decode_recipe, with_fiber,
+};
+use export::{to_markdown, to_plain_summary};
+use import::{parse_csv, parse_label, parse_table};
+use leptos::ev;
 use leptos::prelude::event_target_value;
 use leptos::prelude::*;
-use serde::{Deserialize, Serialize};
-use wasm_bindgen::JsValue;
-use web_sys::window;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{HtmlDetailsElement, Storage, window};
+
+/// Prefix for every key this app writes to `localStorage`.
+const STORAGE_PREFIX: &str = "pedietcalc.";
+
+/// Optional link-shortener endpoint, set at build time with
+/// `PEDIETCALC_SHORTENER_URL=https://... trunk build`. Without it the "Get
+/// short link" button is hidden and only the inline `#recipe=` link is used.
+const SHORTENER_ENDPOINT: Option<&str> = option_env!("PEDIETCALC_SHORTENER_URL");
+
+/// Nutrition lookup for scanned barcodes; `{code}` is replaced with the
+/// barcode. Override at build time with `PEDIETCALC_BARCODE_URL`.
+const BARCODE_ENDPOINT: &str = match option_env!("PEDIETCALC_BARCODE_URL") {
+    Some(url) => url,
+    None => "https://world.openfoodfacts.org/api/v2/product/{code}.json",
+};
+const UNDO_TOAST_DURATION: Duration = Duration::from_secs(5);
+/// How long the totals show their "updated" pulse after a change.
+const TOTALS_PULSE_DURATION: Duration = Duration::from_millis(700);
+/// Samples kept for the session's ratio sparkline.
+const RATIO_HISTORY_LIMIT: usize = 40;
+/// How long totals must stay unchanged before the sparkline samples them.
+const RATIO_SAMPLE_DELAY: Duration = Duration::from_secs(1);
+/// Length of the count-up when a total jumps, in milliseconds.
+const COUNT_UP_MS: f64 = 400.0;
+/// How far (in CSS pixels) a card must be swiped left to remove it.
+const SWIPE_REMOVE_DISTANCE: f64 = 120.0;
+/// Element in index.html the app mounts into, replacing its loading shell.
+const APP_ROOT_ID: &str = "app";
+
+/// Share links longer than this risk being truncated by browsers, chat apps,
+/// and URL shorteners.
+const SHARE_URL_LIMIT: usize = 2_048;
+
+/// Fraction of [`SHARE_URL_LIMIT`] at which the share link readout warns.
+const SHARE_URL_WARN_FRACTION: f64 = 0.75;
+
+/// How many previously entered foods the name autocomplete remembers.
+const RECENT_FOODS_LIMIT: usize = 50;
 
 #[derive(Clone, Debug, PartialEq)]
 struct Ingredient {
@@ -13,20 +73,121 @@ struct Ingredient {
     protein: String,
     fat: String,
     net_carbs: String,
+    sat_fat: String,
+    total_carbs: String,
+    fiber: String,
+    sugar_alcohols: String,
     servings: String,
+    base_amount: String,
+    unit: String,
+    servings_per_container: String,
+    by_container: bool,
+    basis: Basis,
+    note: String,
+    locked: bool,
 }
 
 impl Ingredient {
     fn empty(id: usize) -> Self {
+        Self::with_servings(id, "1")
+    }
+
+    /// A blank card starting at `servings`, from the default-servings setting.
+    fn with_servings(id: usize, servings: &str) -> Self {
         Self {
             id,
             name: String::new(),
             protein: String::new(),
             fat: String::new(),
             net_carbs: String::new(),
-            servings: "1".to_string(),
+            sat_fat: String::new(),
+            total_carbs: String::new(),
+            fiber: String::new(),
+            sugar_alcohols: String::new(),
+            servings: servings.to_string(),
+            base_amount: String::new(),
+            unit: String::new(),
+            servings_per_container: String::new(),
+            by_container: false,
+            basis: Basis::PerServing,
+            note: String::new(),
+            locked: false,
+        }
+    }
+
+    /// A card with some macros filled in but others left blank, which the
+    /// strict totals mode treats as unknown rather than zero.
+    fn has_blank_macro(&self) -> bool {
+        let fields = [&self.protein, &self.fat, &self.net_carbs];
+        let blank = |value: &&String| value.trim().is_empty();
+        fields.iter().any(blank) && !fields.iter().all(blank)
+    }
+
+    /// Recomputes net carbs from the label breakdown, if one was entered.
+    fn apply_carb_breakdown(&mut self, sugar_alcohol_factor: f64) {
+        if self.total_carbs.trim().is_empty() {
+            return;
+        }
+        self.net_carbs = format_input_value(net_carbs_from(
+            parse_quantity(&self.total_carbs),
+            parse_quantity(&self.fiber),
+            parse_quantity(&self.sugar_alcohols),
+            sugar_alcohol_factor,
+        ));
+    }
+
+    /// Suffix for the macro fields, e.g. "per 100 g" or "per 3 crackers".
+    fn macro_label(&self) -> String {
+        custom_macro_label(self.basis, &self.base_amount, &self.unit)
+    }
+
+    /// Servings in one container when the amount counts containers.
+    fn per_container(&self) -> Option<f64> {
+        container_servings(self.basis, self.by_container, &self.servings_per_container)
+    }
+
+    /// Factor applied to the label macros for `amount` used.
+    fn multiplier(&self, amount: f64) -> f64 {
+        amount_multiplier(self.basis, amount, &self.base_amount, self.per_container())
+    }
+
+    fn amount_label(&self) -> String {
+        if self.per_container().is_some() {
+            return "Containers used in recipe".to_string();
+        }
+        custom_amount_label(self.basis, &self.base_amount, &self.unit)
+    }
+
+    /// Unit shown beside the amount field.
+    fn amount_suffix(&self) -> String {
+        if self.per_container().is_some() {
+            "containers".to_string()
+        } else if custom_base(self.basis, &self.base_amount).is_some() {
+            self.unit.trim().to_string()
+        } else if self.basis.is_per_serving() {
+            "servings".to_string()
+        } else {
+            "g".to_string()
         }
     }
+
+    /// Macros are entered but the amount used is zero or blank, which is
+    /// almost always a slip: the food then adds nothing to the recipe.
+    fn has_zero_amount(&self) -> bool {
+        [&self.protein, &self.fat, &self.net_carbs]
+            .into_iter()
+            .any(|value| parse_quantity(value) > 0.0)
+            && parse_quantity(&self.servings) <= 0.0
+    }
+
+    /// Whether removing this card would lose anything the user typed.
+    fn has_data(&self) -> bool {
+        !self.name.trim().is_empty()
+            || !self.note.trim().is_empty()
+            || [&self.protein, &self.fat, &self.net_carbs]
+                .into_iter()
+                .any(|value| parse_quantity(value) > 0.0)
+    }
 }
 
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -35,141 +196,1758 @@ struct RowSnapshot {
     per_protein: f64,
     per_fat: f64,
     per_carbs: f64,
+    per_fiber: f64,
     servings: f64,
+    /// The amount as typed, e.g. "3/4".
+    servings_text: String,
+    note: String,
+    basis: Basis,
+    base_amount: String,
+    unit: String,
+    per_container: Option<f64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct RecipePayload {
-    name: Option<String>,
-    ingredients: Vec<IngredientPayload>,
+/// Editable recipe state restored from a [`RecipePayload`].
+struct RecipeState {
+    ingredients: Vec<Ingredient>,
+    name: String,
+    notes: String,
+    ui: Option<UiPrefs>,
+    locked: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct IngredientPayload {
-    id: usize,
-    name: String,
-    protein: f64,
-    fat: f64,
-    net_carbs: f64,
-    servings: f64,
+impl RecipeState {
+    fn payload(&self) -> RecipePayload {
+        recipe_payload(
+            &self.ingredients,
+            &self.name,
+            &self.notes,
+            self.ui,
+            self.locked,
+        )
+    }
+}
+
+impl RowSnapshot {
+    fn multiplier(&self) -> f64 {
+        amount_multiplier(
+            self.basis,
+            self.servings,
+            &self.base_amount,
+            self.per_container,
+        )
+    }
+
+    fn macro_label(&self) -> String {
+        custom_macro_label(self.basis, &self.base_amount, &self.unit)
+    }
+
+    fn amount_unit(&self) -> String {
+        if self.per_container.is_some() {
+            return " containers".to_string();
+        }
+        custom_amount_unit(self.basis, &self.base_amount, &self.unit)
+    }
 }
 
 #[component]
 pub fn App() -> impl IntoView {
-    let (initial_ingredients, initial_name) =
-        load_recipe_from_url().unwrap_or_else(|| (vec![Ingredient::empty(0)], String::new()));
-    let initial_next_id = initial_ingredients
-        .iter()
-        .map(|ingredient| ingredient.id)
-        .max()
-        .map(|max_id| max_id + 1)
-        .unwrap_or(1);
+    let (shared_payload, invalid_link) = match load_payload_from_url() {
+        Some(Ok(payload)) => (Some(payload), false),
+        Some(Err(error)) => {
+            leptos::logging::warn!("Ignoring the recipe in the link: {error}");
+            (None, true)
+        }
+        None => (None, false),
+    };
+    let invalid_link = RwSignal::new(invalid_link);
+    // Leave a broken link in the address bar until the recipe is edited, so
+    // it can still be copied for a bug report.
+    let keep_link = StoredValue::new(invalid_link.get_untracked());
+    let default_servings = persisted_signal("default_servings", "1".to_string());
+    let initial = shared_payload
+        .clone()
+        .map(recipe_from_payload)
+        .unwrap_or_else(|| RecipeState {
+            ingredients: vec![Ingredient::with_servings(
+                0,
+                &default_servings.get_untracked(),
+            )],
+            name: String::new(),
+            notes: String::new(),
+            ui: None,
+            locked: false,
+        });
+    let initial_next_id = next_free_id(&initial.ingredients);
 
-    let (ingredients, set_ingredients) = signal(initial_ingredients);
+    let (ingredients, set_ingredients) = signal(initial.ingredients);
+    Effect::new(move || {
+        ingredients.with(|items| {
+            debug_assert!(has_unique_ids(items), "duplicate ingredient ids: {items:?}");
+        });
+    });
     let next_id = RwSignal::new(initial_next_id);
-    let (recipe_name, set_recipe_name) = signal(initial_name);
+    let (recipe_name, set_recipe_name) = signal(initial.name);
+    let notes = RwSignal::new(initial.notes);
+    let recipe_ui = RwSignal::new(initial.ui);
+    let recipe_locked = RwSignal::new(initial.locked);
+    let original: RwSignal<Option<RecipePayload>> = RwSignal::new(shared_payload);
+    let storage_full = RwSignal::new(false);
+    provide_context(StorageFull(storage_full));
+    let target_protein = persisted_signal("target.protein", String::new());
+    let target_fat = persisted_signal("target.fat", String::new());
+    let target_carbs = persisted_signal("target.net_carbs", String::new());
+    let rounding_setting = persisted_signal("rounding", RoundMode::Nearest);
+    let ratio_setting = persisted_signal("ratio", RatioMode::Energy);
+    // Display settings the viewer picked in this session. These win over a
+    // recipe's own preferences, which in turn win over the saved settings.
+    let local_ui = RwSignal::new(UiPrefs::default());
+    let rounding = Memo::new(move |_| {
+        local_ui
+            .get()
+            .rounding
+            .or_else(|| recipe_ui.with(|ui| ui.and_then(|ui| ui.rounding)))
+            .unwrap_or_else(|| rounding_setting.get())
+    });
+    let ratio_mode = Memo::new(move |_| {
+        local_ui
+            .get()
+            .ratio
+            .or_else(|| recipe_ui.with(|ui| ui.and_then(|ui| ui.ratio)))
+            .unwrap_or_else(|| ratio_setting.get())
+    });
+    let help_open = persisted_signal("help.open", false);
+    let print_shortcut = persisted_signal("shortcut.print", true);
+    let high_energy_percent = persisted_signal("highlight.energy_percent", 30.0_f64);
+    let print_energy = persisted_signal("print.energy_column", true);
+    let print_hide_unnamed = persisted_signal("print.hide_unnamed", false);
+    let print_bands = persisted_signal("print.pe_bands", false);
+    let summary_in_kcal = persisted_signal("summary.kcal", false);
+    let pin_totals = persisted_signal("summary.pinned", false);
+    let group_totals = persisted_signal("summary.grouped", false);
+    let strict_blanks = persisted_signal("totals.strict", false);
+    let numbers_only = persisted_signal("input.numbers_only", false);
+    let high_contrast = persisted_signal("contrast.high", prefers_more_contrast());
+    let barcode_enabled = persisted_signal("barcode.enabled", false);
+    let tab_skips_remove = persisted_signal("tab.skip_remove", true);
+    // Hides the intro paragraphs for returning users.
+    let focus_mode = persisted_signal("header.focus", false);
+    let fiber_in_ratio = persisted_signal("ratio.include_fiber", false);
+    let compact_view = persisted_signal("view.compact", false);
+    let keto_max_carbs = persisted_signal("diet.keto_max_carbs", 20.0_f64);
+    let high_carb_min = persisted_signal("diet.high_carb_min", 50.0_f64);
+    let portions = RwSignal::new("1".to_string());
+    let show_unbounded = persisted_signal("ratio.show_unbounded", false);
+    let unbounded_symbol = persisted_signal("ratio.unbounded_symbol", "∞".to_string());
+    // What a ratio with protein but no fat or carbs shows. Off by default, so
+    // it reads "—" like an empty recipe.
+    let unbounded_ratio = move || {
+        let symbol = unbounded_symbol.get();
+        match symbol.trim() {
+            _ if !show_unbounded.get() => "—".to_string(),
+            "" => "∞".to_string(),
+            symbol => symbol.to_string(),
+        }
+    };
+    // Fiber grams to add to the energy term of displayed ratios.
+    let ratio_fiber = move |fiber: f64| if fiber_in_ratio.get() { fiber } else { 0.0 };
+    let ratio_style = move || RatioStyle {
+        kind: ratio_mode.get(),
+        include_fiber: fiber_in_ratio.get(),
+        unbounded: unbounded_ratio(),
+    };
+    // `totals` and `fiber` grams as the ratio settings show them.
+    let shown_ratio = move |totals: (f64, f64, f64), fiber: f64| {
+        ratio_style().format(totals, fiber, rounding.get())
+    };
+    let recent_foods = RwSignal::new(load_recent_foods());
+    let sugar_alcohol_factor = persisted_signal("carbs.sugar_alcohol_factor", 0.5_f64);
+    // A locked recipe opens read-only unless the link asks to edit it.
+    let read_only = RwSignal::new(match query_param("mode").as_deref() {
+        Some("view") => true,
+        Some("edit") => false,
+        _ => recipe_locked.get_untracked(),
+    });
+    let debug = query_param("debug").as_deref() == Some("1");
+    // `?embed=totals` renders only the totals, for use in an iframe.
+    let embed = query_param("embed").as_deref() == Some("totals");
+    let search = RwSignal::new(String::new());
+    let collapsed: RwSignal<HashSet<usize>> = RwSignal::new(HashSet::new());
+    let selected: RwSignal<HashSet<usize>> = RwSignal::new(HashSet::new());
+    let servings_overrides: RwSignal<HashMap<usize, f64>> = RwSignal::new(HashMap::new());
+    let merge_message: RwSignal<Option<String>> = RwSignal::new(None);
+    let auto_fill_error: RwSignal<Option<String>> = RwSignal::new(None);
+    let pasted_label = RwSignal::new(String::new());
+    let label_error = RwSignal::new(None::<String>);
+    let protein_goal = RwSignal::new(String::new());
+    let equal_servings = RwSignal::new(String::new());
+    let macro_adjust_percent = RwSignal::new(String::new());
+    let short_link: RwSignal<Option<Result<String, String>>> = RwSignal::new(None);
+    let short_link_pending = RwSignal::new(false);
+    let import_link = RwSignal::new(String::new());
+    let import_error: RwSignal<Option<String>> = RwSignal::new(None);
+    let import_csv = RwSignal::new(String::new());
+    let pasted_table = RwSignal::new(String::new());
+    let paste_replaces = RwSignal::new(false);
+    let paste_errors: RwSignal<Vec<String>> = RwSignal::new(Vec::new());
+    let import_csv_error: RwSignal<Option<String>> = RwSignal::new(None);
+    let comparison_link = RwSignal::new(String::new());
+    let comparison: RwSignal<Option<(String, Vec<Ingredient>)>> = RwSignal::new(None);
+    let comparison_error: RwSignal<Option<String>> = RwSignal::new(None);
 
     let add_ingredient = {
         move |_| {
-            let id = next_id.get_untracked();
-            next_id.update(|value| *value += 1);
-            set_ingredients.update(|items| items.push(Ingredient::empty(id)));
+            let id = fresh_id(next_id);
+            let servings = default_servings.get_untracked();
+            set_ingredients.update(|items| items.push(Ingredient::with_servings(id, &servings)));
         }
     };
 
+    // The table row whose first cell should take focus once it is rendered.
+    let focus_row: RwSignal<Option<usize>> = RwSignal::new(None);
+    let add_row_and_focus = move || {
+        let id = fresh_id(next_id);
+        let servings = default_servings.get_untracked();
+        set_ingredients.update(|items| items.push(Ingredient::with_servings(id, &servings)));
+        focus_row.set(Some(id));
+    };
+
+    // The most recently removed card and where it was, offered back by the
+    // undo toast until it expires.
+    let last_removed: RwSignal<Option<(usize, Ingredient)>> = RwSignal::new(None);
+    let undo_token = RwSignal::new(0_u32);
+
+    let undo_remove = move |_| {
+        let Some((index, mut ingredient)) = last_removed.get_untracked() else {
+            return;
+        };
+        set_ingredients.update(|items| {
+            // Drop the blank card added when the last one was removed.
+            if items.len() == 1 && !items[0].has_data() {
+                items.clear();
+            }
+            if items.iter().any(|item| item.id == ingredient.id) {
+                ingredient.id = fresh_id(next_id);
+            }
+            items.insert(index.min(items.len()), ingredient);
+        });
+        last_removed.set(None);
+    };
+
     let remove_ingredient = {
         move |id: usize| {
+            let needs_confirmation = ingredients.with_untracked(|items| {
+                items
+                    .iter()
+                    .find(|item| item.id == id)
+                    .is_some_and(Ingredient::has_data)
+            });
+            if needs_confirmation && !confirm("Remove this ingredient and its values?") {
+                return;
+            }
+            selected.update(|ids| {
+                ids.remove(&id);
+            });
             set_ingredients.update(|items| {
+                if let Some(index) = items.iter().position(|item| item.id == id) {
+                    let token = undo_token.get_untracked() + 1;
+                    undo_token.set(token);
+                    last_removed.set(Some((index, items[index].clone())));
+                    set_timeout(
+                        move || {
+                            if undo_token.get_untracked() == token {
+                                last_removed.set(None);
+                            }
+                        },
+                        UNDO_TOAST_DURATION,
+                    );
+                }
                 items.retain(|item| item.id != id);
                 if items.is_empty() {
-                    let new_id = next_id.get_untracked();
-                    next_id.update(|value| *value += 1);
-                    items.push(Ingredient::empty(new_id));
+                    items.push(Ingredient::with_servings(
+                        fresh_id(next_id),
+                        &default_servings.get_untracked(),
+                    ));
                 }
             });
         }
     };
 
-    let print_recipe = |_| {
-        if let Some(win) = window() {
-            let _ = win.print();
+    let delete_selected = move |_| {
+        let ids = selected.get_untracked();
+        if ids.is_empty() {
+            return;
         }
+        let message = match ids.len() {
+            1 => "Delete the selected ingredient?".to_string(),
+            count => format!("Delete {count} selected ingredients?"),
+        };
+        if !confirm(&message) {
+            return;
+        }
+        set_ingredients.update(|items| {
+            items.retain(|item| !ids.contains(&item.id));
+            if items.is_empty() {
+                items.push(Ingredient::with_servings(
+                    fresh_id(next_id),
+                    &default_servings.get_untracked(),
+                ));
+            }
+        });
+        selected.update(HashSet::clear);
     };
 
-    Effect::new({
-        let ingredients = ingredients;
-        let recipe_name = recipe_name;
-        move || {
-            let current = ingredients.get();
-            let name = recipe_name.get();
-            if let Some(encoded) = encode_recipe(&current, &name) {
-                let target_hash = format!("#recipe={encoded}");
-                if let Some(win) = window() {
-                    let location = win.location();
-                    if location.hash().unwrap_or_default() != target_hash {
-                        if let Ok(history) = win.history() {
-                            let _ = history.replace_state_with_url(
-                                &JsValue::NULL,
-                                "",
-                                Some(&format!(
-                                    "{}{}{}",
-                                    location.pathname().unwrap_or_default(),
-                                    location.search().unwrap_or_default(),
-                                    target_hash
-                                )),
-                            );
-                        } else {
-                            let _ = location.set_hash(&target_hash);
-                        }
-                    }
+    let make_copy = move |_| {
+        remove_query_param("mode");
+        set_ingredients.update(|items| next_id.set(renumber_ids(items)));
+        collapsed.update(HashSet::clear);
+        selected.update(HashSet::clear);
+        set_recipe_name.update(|name| {
+            let trimmed = name.trim();
+            *name = if trimmed.is_empty() {
+                "Untitled recipe (copy)".to_string()
+            } else {
+                format!("{trimmed} (copy)")
+            };
+        });
+        read_only.set(false);
+        recipe_locked.set(false);
+        original.set(None);
+    };
+
+    let new_from_template = move |_| {
+        if !confirm("Start a new recipe with the same foods and amounts but blank macros?") {
+            return;
+        }
+        set_ingredients.update(|items| {
+            *items = blank_macros(items);
+            next_id.set(renumber_ids(items));
+        });
+        collapsed.update(HashSet::clear);
+        selected.update(HashSet::clear);
+        servings_overrides.update(HashMap::clear);
+        original.set(None);
+    };
+
+    let merge_duplicate_ingredients = move |_| {
+        let mut merged_count = 0;
+        let mut skipped = Vec::new();
+        set_ingredients.update(|items| {
+            let before = items.len();
+            skipped = merge_duplicates(items);
+            merged_count = before - items.len();
+        });
+        collapsed.update(HashSet::clear);
+        selected.update(HashSet::clear);
+        let mut message = match merged_count {
+            0 => "No duplicates to merge.".to_string(),
+            1 => "Merged 1 duplicate card.".to_string(),
+            count => format!("Merged {count} duplicate cards."),
+        };
+        if !skipped.is_empty() {
+            message.push_str(&format!(
+                " Not merged because their label values differ: {}.",
+                skipped.join(", ")
+            ));
+        }
+        merge_message.set(Some(message));
+    };
+
+    let revert_to_original = move |_| {
+        if let Some(payload) = original.get_untracked() {
+            let state = recipe_from_payload(payload);
+            next_id.set(next_free_id(&state.ingredients));
+            set_ingredients.set(state.ingredients);
+            set_recipe_name.set(state.name);
+            notes.set(state.notes);
+            recipe_ui.set(state.ui);
+            recipe_locked.set(state.locked);
+        }
+    };
+
+    let print_recipe = |_| print_page();
+
+    let show_help = RwSignal::new(false);
+    // The ingredient open in the full-screen editor, if any.
+    let editing: RwSignal<Option<usize>> = RwSignal::new(None);
+
+    Effect::new(move || {
+        if let Some(id) = editing.get()
+            && !ingredients.with(|items| items.iter().any(|item| item.id == id))
+        {
+            editing.set(None);
+        }
+    });
+
+    let keydown_handle = window_event_listener(ev::keydown, move |ev| {
+        let modifier = ev.ctrl_key() || ev.meta_key();
+        if print_shortcut.get_untracked()
+            && modifier
+            && !ev.alt_key()
+            && ev.key().eq_ignore_ascii_case("p")
+        {
+            // Suppress the browser's own print so the dialog opens once.
+            ev.prevent_default();
+            print_page();
+        } else if ev.key() == "Escape" && show_help.get_untracked() {
+            show_help.set(false);
+        } else if ev.key() == "Escape" && editing.get_untracked().is_some() {
+            editing.set(None);
+        } else if ev.key() == "?" && !modifier && !is_text_entry(&ev) {
+            ev.prevent_default();
+            show_help.update(|open| *open = !*open);
+        }
+    });
+    on_cleanup(move || keydown_handle.remove());
+
+    // Browsers suggest the page title as the file name for "Save as PDF".
+    let default_title = StoredValue::new(String::new());
+    let beforeprint_handle = window_event_listener(ev::beforeprint, move |_| {
+        let Some(document) = window().and_then(|win| win.document()) else {
+            return;
+        };
+        default_title.set_value(document.title());
+        let name = recipe_name.get_untracked();
+        if !name.trim().is_empty() {
+            document.set_title(&format!("{} – P:E Diet Recipe Calculator", name.trim()));
+        }
+    });
+    let afterprint_handle = window_event_listener(ev::afterprint, move |_| {
+        if let Some(document) = window().and_then(|win| win.document()) {
+            document.set_title(&default_title.get_value());
+        }
+    });
+    on_cleanup(move || {
+        beforeprint_handle.remove();
+        afterprint_handle.remove();
+    });
+
+    let pdf_hint = RwSignal::new(false);
+    let download_pdf = move |_| {
+        pdf_hint.set(true);
+        // Let the hint render before the print dialog blocks the page.
+        request_animation_frame(print_page);
+    };
+
+    let current_payload = Memo::new(move |_| {
+        ingredients.with(|items| {
+            recipe_payload(
+                items,
+                &recipe_name.get(),
+                &notes.get(),
+                recipe_ui.get(),
+                recipe_locked.get(),
+            )
+        })
+    });
+
+    let encoded_recipe = Memo::new(move |_| current_payload.with(encode_payload));
+
+    let get_short_link = move |_| {
+        let Some(endpoint) = SHORTENER_ENDPOINT else {
+            return;
+        };
+        let payload = current_payload.get_untracked();
+        short_link_pending.set(true);
+        leptos::task::spawn_local(async move {
+            let result = create_short_link(&payload, endpoint).await;
+            short_link.set(Some(result));
+            short_link_pending.set(false);
+        });
+    };
+
+    // A short link only matches the recipe it was created for.
+    Effect::new(move || {
+        encoded_recipe.track();
+        short_link.set(None);
+    });
+
+    let share_url_length = Memo::new(move |_| {
+        let location = window()?.location();
+        let base = format!(
+            "{}{}{}",
+            location.origin().unwrap_or_default(),
+            location.pathname().unwrap_or_default(),
+            location.search().unwrap_or_default()
+        );
+        Some(current_payload.with(|payload| build_share_url(&base, payload).len()))
+    });
+
+    let original_encoded = Memo::new(move |_| {
+        original
+            .get()
+            .map(|payload| encode_payload(&recipe_from_payload(payload).payload()))
+    });
+
+    let differs_from_original = Memo::new(move |_| {
+        original_encoded.with(|baseline| {
+            baseline
+                .as_ref()
+                .is_some_and(|baseline| encoded_recipe.with(|current| current != baseline))
+        })
+    });
+
+    let auto_link = persisted_signal("link.auto_update", true);
+    // Bumped by the "Update link" button when automatic updates are off.
+    let link_update = RwSignal::new(0_u32);
+    // The encoded recipe last written to the address bar.
+    let linked_recipe = RwSignal::new(String::new());
+    Effect::new(move || {
+        let Some(win) = window() else {
+            return;
+        };
+        let location = win.location();
+        let base = format!(
+            "{}{}",
+            location.pathname().unwrap_or_default(),
+            location.search().unwrap_or_default()
+        );
+        let hash = location.hash().unwrap_or_default();
+        // With automatic updates off, only the "Update link" button writes.
+        let encoded = if auto_link.get() {
+            encoded_recipe.get()
+        } else {
+            link_update.track();
+            encoded_recipe.get_untracked()
+        };
+        if keep_link.get_value() {
+            keep_link.set_value(false);
+            return;
+        }
+        if debug {
+            let decoded = decode_recipe(&encoded);
+            current_payload.with_untracked(|payload| {
+                if decoded.as_ref() != Some(payload) {
+                    leptos::logging::warn!(
+                        "Share link round-trip mismatch: encoded {payload:?}, decoded {decoded:?}"
+                    );
                 }
+            });
+        }
+        linked_recipe.set(encoded.clone());
+        let mut fragment = Fragment::parse(&hash);
+        fragment.set("recipe", encoded);
+        let fragment = fragment.to_string();
+        let target_url = format!("{base}#{fragment}");
+        let current_url = format!("{base}{hash}");
+        if current_url != target_url {
+            if let Ok(history) = win.history() {
+                let _ = history.replace_state_with_url(&JsValue::NULL, "", Some(&target_url));
+            } else {
+                let _ = location.set_hash(&fragment);
             }
         }
     });
 
+    // Changing the sugar alcohol setting reworks every label breakdown.
+    Effect::new(move |previous: Option<f64>| {
+        let factor = sugar_alcohol_factor.get();
+        if previous.is_some_and(|previous| previous != factor) {
+            set_ingredients.update(|items| {
+                for item in items.iter_mut() {
+                    item.apply_carb_breakdown(factor);
+                }
+            });
+        }
+        factor
+    });
+
     let totals = Memo::new(move |_| {
+        let overrides = servings_overrides.get();
+        ingredients.with(|items| recipe_totals(items, &overrides))
+    });
+
+    // Only shown once some ingredient has saturated fat entered.
+    let sat_fat_total = Memo::new(move |_| {
+        let overrides = servings_overrides.get();
+        ingredients.with(|items| {
+            let entered = items.iter().filter(|item| !item.sat_fat.trim().is_empty());
+            entered.clone().next().is_some().then(|| {
+                entered
+                    .map(|item| {
+                        parse_quantity(&item.sat_fat) * effective_multiplier(item, &overrides)
+                    })
+                    .sum::<f64>()
+            })
+        })
+    });
+
+    // Totals as shown in the summary: they count up to the real totals after
+    // a big jump (adding a food, scaling) and follow small edits directly.
+    let shown_totals = RwSignal::new(totals.get_untracked());
+    let count_up_token = StoredValue::new(0_u32);
+    Effect::new(move || {
+        let target = totals.get();
+        let from = shown_totals.get_untracked();
+        count_up_token.update_value(|token| *token = token.wrapping_add(1));
+        if is_big_jump(from, target) && !prefers_reduced_motion() {
+            let token = count_up_token.get_value();
+            count_up(
+                from,
+                target,
+                js_sys::Date::now(),
+                token,
+                count_up_token,
+                shown_totals,
+            );
+        } else {
+            shown_totals.set(target);
+        }
+    });
+
+    // Briefly set after the totals change, so the summary can show that it
+    // reflects the latest keystroke. Each change restarts the timer.
+    let totals_updated = RwSignal::new(false);
+    let totals_pulse = StoredValue::new(0_u32);
+    Effect::new(move |previous: Option<()>| {
+        totals.track();
+        if previous.is_none() {
+            return;
+        }
+        totals_pulse.update_value(|token| *token = token.wrapping_add(1));
+        let token = totals_pulse.get_value();
+        totals_updated.set(true);
+        set_timeout(
+            move || {
+                if totals_pulse.get_value() == token {
+                    totals_updated.set(false);
+                }
+            },
+            TOTALS_PULSE_DURATION,
+        );
+    });
+
+    let incomplete_ids = Memo::new(move |_| {
+        if !strict_blanks.get() {
+            return HashSet::new();
+        }
+        ingredients.with(|items| {
+            items
+                .iter()
+                .filter(|item| item.has_blank_macro())
+                .map(|item| item.id)
+                .collect::<HashSet<usize>>()
+        })
+    });
+    let approx = move || {
+        if incomplete_ids.with(HashSet::is_empty) {
+            ""
+        } else {
+            "≈ "
+        }
+    };
+
+    // Totals rolled up by ingredient name, ignoring case, without merging
+    // the cards themselves.
+    let grouped_totals = Memo::new(move |_| {
+        let overrides = servings_overrides.get();
+        ingredients.with(|items| group_by_name(items, &overrides))
+    });
+
+    let high_energy_ids = Memo::new(move |_| {
+        let recipe_calories = total_calories(totals.get());
+        let threshold = high_energy_percent.get();
+        let overrides = servings_overrides.get();
+        ingredients.with(|items| {
+            items
+                .iter()
+                .filter(|item| {
+                    let calories =
+                        total_calories(recipe_totals(std::slice::from_ref(item), &overrides));
+                    percent_of(calories, recipe_calories).is_some_and(|share| share > threshold)
+                })
+                .map(|item| item.id)
+                .collect::<HashSet<usize>>()
+        })
+    });
+
+    // Totals of the foods the batch tools leave alone.
+    let locked_totals = Memo::new(move |_| {
+        let overrides = servings_overrides.get();
         ingredients.with(|items| {
-            let mut total_protein = 0.0;
-            let mut total_fat = 0.0;
-            let mut total_carbs = 0.0;
-            for item in items {
-                let servings = parse_quantity(&item.servings);
-                total_protein += parse_quantity(&item.protein) * servings;
-                total_fat += parse_quantity(&item.fat) * servings;
-                total_carbs += parse_quantity(&item.net_carbs) * servings;
+            let locked = items
+                .iter()
+                .filter(|item| item.locked)
+                .cloned()
+                .collect::<Vec<_>>();
+            recipe_totals(&locked, &overrides)
+        })
+    });
+
+    let locked_fiber = Memo::new(move |_| {
+        let overrides = servings_overrides.get();
+        ingredients.with(|items| {
+            let locked = items
+                .iter()
+                .filter(|item| item.locked)
+                .cloned()
+                .collect::<Vec<_>>();
+            recipe_fiber(&locked, &overrides)
+        })
+    });
+
+    // Fiber entered in the label breakdowns, which weighs but isn't a macro.
+    let total_fiber = Memo::new(move |_| {
+        let overrides = servings_overrides.get();
+        ingredients.with(|items| recipe_fiber(items, &overrides))
+    });
+
+    let snapped_totals = Memo::new(move |_| {
+        ingredients.with(|items| {
+            let snapped = items
+                .iter()
+                .cloned()
+                .map(|mut item| {
+                    if !item.locked {
+                        item.servings = snapped_servings(&item);
+                    }
+                    item
+                })
+                .collect::<Vec<_>>();
+            (
+                recipe_totals(&snapped, &HashMap::new()),
+                recipe_fiber(&snapped, &HashMap::new()),
+            )
+        })
+    });
+
+    let snap_servings = move |_| {
+        set_ingredients.update(|items| {
+            for item in items.iter_mut().filter(|item| !item.locked) {
+                item.servings = snapped_servings(item);
             }
-            (total_protein, total_fat, total_carbs)
+        });
+    };
+
+    let equalize_servings = move |_| {
+        let value = canonical_quantity(&equal_servings.get_untracked());
+        if value.is_empty() {
+            return;
+        }
+        let unlocked = ingredients.with_untracked(|items| {
+            items
+                .iter()
+                .filter(|item| !item.locked)
+                .map(|item| item.id)
+                .collect::<Vec<_>>()
+        });
+        for id in unlocked {
+            let value = value.clone();
+            update_ingredient(set_ingredients, id, |item| item.servings = value);
+        }
+        servings_overrides.update(HashMap::clear);
+    };
+
+    // Factor for "Adjust all macros by %", e.g. 1.07 for "7" or "+7%".
+    let macro_adjust_factor = Memo::new(move |_| {
+        macro_adjust_percent.with(|raw| {
+            let percent = raw
+                .trim()
+                .trim_end_matches('%')
+                .trim()
+                .parse::<f64>()
+                .ok()?;
+            let factor = 1.0 + percent / 100.0;
+            (factor.is_finite() && factor > 0.0 && percent != 0.0).then_some(factor)
         })
     });
 
+    let adjusted_macro_totals = Memo::new(move |_| {
+        let factor = macro_adjust_factor.get()?;
+        let (protein, fat, carbs) = totals.get();
+        let locked = locked_totals.get();
+        Some((
+            locked.0 + (protein - locked.0) * factor,
+            locked.1 + (fat - locked.1) * factor,
+            locked.2 + (carbs - locked.2) * factor,
+        ))
+    });
+
+    let adjust_all_macros = move |_| {
+        let Some(factor) = macro_adjust_factor.get_untracked() else {
+            return;
+        };
+        let unlocked = ingredients.with_untracked(|items| {
+            items
+                .iter()
+                .filter(|item| !item.locked)
+                .map(|item| item.id)
+                .collect::<Vec<_>>()
+        });
+        for id in unlocked {
+            update_ingredient(set_ingredients, id, |item| {
+                // The label breakdown is scaled too so net carbs stay
+                // consistent with it.
+                for value in [
+                    &mut item.protein,
+                    &mut item.fat,
+                    &mut item.net_carbs,
+                    &mut item.sat_fat,
+                    &mut item.total_carbs,
+                    &mut item.fiber,
+                    &mut item.sugar_alcohols,
+                ] {
+                    *value = scaled_quantity(value, factor);
+                }
+            });
+        }
+        macro_adjust_percent.set(String::new());
+    };
+
+    let auto_fill_servings = move |_| {
+        // Locked foods stay as they are; the solver fills the rest of each
+        // target around them.
+        let (locked_protein, locked_fat, locked_carbs) = locked_totals.get_untracked();
+        let targets = [
+            (target_protein, locked_protein),
+            (target_fat, locked_fat),
+            (target_carbs, locked_carbs),
+        ]
+        .map(|(target, locked)| {
+            let raw = target.get_untracked();
+            (!raw.trim().is_empty()).then(|| parse_quantity(&raw) - locked)
+        });
+        let per_unit = ingredients.with_untracked(|items| {
+            items
+                .iter()
+                .filter(|item| !item.locked)
+                .map(|item| {
+                    let unit = item.multiplier(1.0);
+                    [&item.protein, &item.fat, &item.net_carbs]
+                        .map(|raw| parse_quantity(raw) * unit)
+                })
+                .collect::<Vec<_>>()
+        });
+        match solve_recipe(targets, &per_unit) {
+            Some(amounts) => {
+                set_ingredients.update(|items| {
+                    let unlocked = items.iter_mut().filter(|item| !item.locked);
+                    for (item, amount) in unlocked.zip(amounts) {
+                        item.servings =
+                            format_input_value(round_hundredths(amount, RoundMode::Nearest));
+                    }
+                });
+                servings_overrides.update(HashMap::clear);
+                auto_fill_error.set(None);
+            }
+            None => auto_fill_error.set(Some(
+                "Set at least one daily target and enter macros for the unlocked foods first."
+                    .to_string(),
+            )),
+        }
+    };
+
+    // Factor for the unlocked foods that brings total protein to
+    // `protein_goal`, if both are set and the locked foods don't already
+    // exceed it.
+    let protein_scale = Memo::new(move |_| {
+        let (protein, _, _) = totals.get();
+        let (locked_protein, _, _) = locked_totals.get();
+        let scalable = protein - locked_protein;
+        let goal = protein_goal.with(|raw| parse_quantity(raw)) - locked_protein;
+        (scalable > f64::MIN_POSITIVE && goal > 0.0).then(|| goal / scalable)
+    });
+
+    // Multiplies every unlocked food's servings by `scale`.
+    let scale_unlocked_servings = move |scale: f64| {
+        let overrides = servings_overrides.get_untracked();
+        set_ingredients.update(|items| {
+            for item in items.iter_mut().filter(|item| !item.locked) {
+                let servings = effective_servings(item, &overrides) * scale;
+                item.servings = format_input_value(round_hundredths(servings, RoundMode::Nearest));
+            }
+        });
+        servings_overrides.update(HashMap::clear);
+    };
+
+    let scale_to_protein = move |_| {
+        if let Some(scale) = protein_scale.get_untracked() {
+            scale_unlocked_servings(scale);
+        }
+    };
+
+    let calorie_goal = RwSignal::new(String::new());
+    let calorie_scale_message = RwSignal::new(None::<String>);
+    let calorie_scale = Memo::new(move |_| {
+        let locked = total_calories(locked_totals.get());
+        let scalable = total_calories(totals.get()) - locked;
+        let goal = calorie_goal.with(|raw| parse_quantity(raw)) - locked;
+        (scalable > f64::MIN_POSITIVE && goal > 0.0).then(|| goal / scalable)
+    });
+
+    let scale_to_calories = move |_| {
+        let Some(scale) = calorie_scale.get_untracked() else {
+            return;
+        };
+        scale_unlocked_servings(scale);
+        let calories = total_calories(totals.get_untracked());
+        let portions = parse_quantity(&portions.get_untracked());
+        let mode = rounding.get_untracked();
+        calorie_scale_message.set(Some(if portions > 0.0 {
+            format!(
+                "Now {} in total, {} per portion.",
+                format_kcal(calories, mode),
+                format_kcal(calories / portions, mode),
+            )
+        } else {
+            format!("Now {} in total.", format_kcal(calories, mode))
+        }));
+    };
+
+    let copy_message = RwSignal::new(None::<String>);
+    let copy_markdown = move |_| {
+        let text = current_payload.with_untracked(|payload| {
+            to_markdown(
+                &payload.ingredients,
+                &recipe_name.get_untracked(),
+                totals.get_untracked(),
+                total_fiber.get_untracked(),
+                rounding.get_untracked(),
+                &untrack(ratio_style),
+            )
+        });
+        leptos::task::spawn_local(async move {
+            copy_message.set(Some(if copy_to_clipboard(&text).await {
+                "Copied the recipe as a Markdown table.".to_string()
+            } else {
+                "Couldn't copy to the clipboard.".to_string()
+            }));
+        });
+    };
+
+    let library = RwSignal::new(load_library());
+    let library_message = RwSignal::new(None::<String>);
+    let library_replaces = RwSignal::new(false);
+
+    let save_recipe_to_library = move |_| {
+        let saved = SavedRecipe {
+            recipe: current_payload.get_untracked(),
+            modified_at: js_sys::Date::now(),
+            favorite: false,
+        };
+        let exists = library.with_untracked(|entries| {
+            entries
+                .iter()
+                .any(|entry| entry.name().eq_ignore_ascii_case(saved.name()))
+        });
+        if exists && !confirm(&format!("Replace the saved “{}”?", saved.name())) {
+            return;
+        }
+        library.update(|entries| {
+            save_to_library(entries, saved.recipe, saved.modified_at);
+            store_library(entries);
+        });
+    };
+
+    let open_saved_recipe = move |recipe: RecipePayload| {
+        let unsaved = ingredients.with_untracked(|items| items.iter().any(Ingredient::has_data));
+        if unsaved && !confirm("Replace the current recipe with the saved one?") {
+            return;
+        }
+        let state = recipe_from_payload(recipe);
+        next_id.set(next_free_id(&state.ingredients));
+        set_ingredients.set(state.ingredients);
+        set_recipe_name.set(state.name);
+        notes.set(state.notes);
+        recipe_ui.set(state.ui);
+        recipe_locked.set(state.locked);
+        read_only.set(state.locked);
+        collapsed.update(HashSet::clear);
+        selected.update(HashSet::clear);
+        servings_overrides.update(HashMap::clear);
+        original.set(None);
+    };
+
+    let toggle_favorite = move |name: String| {
+        library.update(|entries| {
+            if let Some(entry) = entries.iter_mut().find(|entry| entry.name() == name) {
+                entry.favorite = !entry.favorite;
+            }
+            sort_library(entries);
+            store_library(entries);
+        });
+    };
+
+    let export_library = move |_| {
+        let json = library.with_untracked(serde_json::to_string_pretty);
+        if let Ok(json) = json {
+            download_text("pedietcalc-library.json", &json, "application/json");
+        }
+    };
+
+    let import_library = move |ev: leptos::ev::Event| {
+        let Some(input) = ev
+            .target()
+            .and_then(|target| target.dyn_into::<web_sys::HtmlInputElement>().ok())
+        else {
+            return;
+        };
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        // Lets the same file be picked again later.
+        input.set_value("");
+        leptos::task::spawn_local(async move {
+            let imported = wasm_bindgen_futures::JsFuture::from(file.text())
+                .await
+                .ok()
+                .and_then(|text| text.as_string())
+                .and_then(|text| serde_json::from_str::<Vec<SavedRecipe>>(&text).ok());
+            let Some(mut imported) = imported else {
+                library_message.set(Some("That file isn't a saved-recipe backup.".to_string()));
+                return;
+            };
+            let count = imported.len();
+            if library_replaces.get_untracked() {
+                let current = library.with_untracked(Vec::len);
+                if current > 0
+                    && !confirm(&format!(
+                        "Replace all {current} saved recipes with the {count} in this file?"
+                    ))
+                {
+                    return;
+                }
+                sort_library(&mut imported);
+                library.update(|entries| {
+                    *entries = imported;
+                    store_library(entries);
+                });
+                library_message.set(Some(format!("Restored {count} saved recipes.")));
+            } else {
+                let mut renamed = 0;
+                library.update(|entries| {
+                    renamed = merge_library(entries, imported);
+                    store_library(entries);
+                });
+                let mut message = format!("Added {count} saved recipes.");
+                if renamed > 0 {
+                    message.push_str(&format!(
+                        " {renamed} had a name already in use and got a number added."
+                    ));
+                }
+                library_message.set(Some(message));
+            }
+        });
+    };
+
+    let delete_saved_recipe = move |name: String| {
+        if !confirm(&format!("Delete “{name}” from this browser?")) {
+            return;
+        }
+        library.update(|entries| {
+            entries.retain(|entry| entry.name() != name);
+            store_library(entries);
+        });
+    };
+
+    let copy_summary = move |_| {
+        let text = to_plain_summary(
+            &recipe_name.get_untracked(),
+            totals.get_untracked(),
+            total_fiber.get_untracked(),
+            rounding.get_untracked(),
+            &untrack(ratio_style),
+        );
+        leptos::task::spawn_local(async move {
+            copy_message.set(Some(if copy_to_clipboard(&text).await {
+                format!("Copied: {text}")
+            } else {
+                "Couldn't copy to the clipboard.".to_string()
+            }));
+        });
+    };
+
+    let append_ingredients = move |payloads: Vec<IngredientPayload>| {
+        set_ingredients.update(|items| {
+            for payload in payloads {
+                let mut ingredient = Ingredient::from(payload);
+                ingredient.apply_carb_breakdown(sugar_alcohol_factor.get_untracked());
+                ingredient.id = fresh_id(next_id);
+                items.push(ingredient);
+            }
+        });
+    };
+
+    let import_from_link = move |_| {
+        let Some(payload) = parse_share_url(&import_link.get_untracked()) else {
+            import_error.set(Some(
+                "That doesn't look like a recipe link from this calculator.".to_string(),
+            ));
+            return;
+        };
+        append_ingredients(payload.ingredients);
+        import_link.set(String::new());
+        import_error.set(None);
+    };
+
+    let import_pasted_table = move |_| {
+        let table = parse_table(&pasted_table.get_untracked());
+        if table.ingredients.is_empty() && table.errors.is_empty() {
+            paste_errors.set(vec!["Paste some rows first.".to_string()]);
+            return;
+        }
+        if paste_replaces.get_untracked() && !table.ingredients.is_empty() {
+            set_ingredients.set(Vec::new());
+            next_id.set(0);
+            collapsed.update(HashSet::clear);
+            selected.update(HashSet::clear);
+            servings_overrides.update(HashMap::clear);
+        }
+        append_ingredients(table.ingredients);
+        if table.errors.is_empty() {
+            pasted_table.set(String::new());
+        }
+        paste_errors.set(table.errors);
+    };
+
+    // Remembers a fully entered food for the name autocomplete, replacing any
+    // older entry with the same name.
+    let remember_food = move |item: &Ingredient| {
+        let name = item.name.trim();
+        let complete = [&item.protein, &item.fat, &item.net_carbs]
+            .iter()
+            .all(|value| !value.trim().is_empty());
+        if name.is_empty() || !complete {
+            return;
+        }
+        let food = IngredientPayload {
+            id: 0,
+            name: name.to_string(),
+            protein: canonical_quantity(&item.protein),
+            fat: canonical_quantity(&item.fat),
+            net_carbs: canonical_quantity(&item.net_carbs),
+            sat_fat: canonical_quantity(&item.sat_fat),
+            total_carbs: canonical_quantity(&item.total_carbs),
+            fiber: canonical_quantity(&item.fiber),
+            sugar_alcohols: canonical_quantity(&item.sugar_alcohols),
+            servings: canonical_quantity(&item.servings),
+            base_amount: canonical_quantity(&item.base_amount),
+            unit: item.unit.trim().to_string(),
+            servings_per_container: canonical_quantity(&item.servings_per_container),
+            by_container: false,
+            basis: item.basis,
+            note: String::new(),
+            locked: false,
+        };
+        recent_foods.update(|foods| {
+            foods.retain(|existing| !existing.name.eq_ignore_ascii_case(&food.name));
+            foods.insert(0, food);
+            foods.truncate(RECENT_FOODS_LIMIT);
+            if let Ok(json) = serde_json::to_string(foods) {
+                save_setting("recent_foods", &json);
+            }
+        });
+    };
+
+    let scanning = RwSignal::new(false);
+    let scan_status = RwSignal::new(None::<String>);
+    let on_barcode = Callback::new(move |code: String| {
+        scanning.set(false);
+        scan_status.set(Some(format!("Looking up barcode {code}…")));
+        leptos::task::spawn_local(async move {
+            match lookup_barcode(&code, BARCODE_ENDPOINT).await {
+                Some(payload) => {
+                    scan_status.set(Some(format!(
+                        "Added {} (per 100 g); set the grams used.",
+                        payload.name
+                    )));
+                    append_ingredients(vec![payload]);
+                }
+                None => scan_status.set(Some(format!(
+                    "No nutrition data found for barcode {code}. Enter it by hand instead."
+                ))),
+            }
+        });
+    });
+
+    let import_from_label = move |_| match parse_label(&pasted_label.get_untracked()) {
+        Some(payload) => {
+            append_ingredients(vec![payload]);
+            pasted_label.set(String::new());
+            label_error.set(None);
+        }
+        None => label_error.set(Some(
+            "Couldn't find protein, fat or carbohydrate lines in that label.".to_string(),
+        )),
+    };
+
+    let import_from_csv = move |_| match parse_csv(&import_csv.get_untracked()) {
+        Ok(payloads) => {
+            append_ingredients(payloads);
+            import_csv.set(String::new());
+            import_csv_error.set(None);
+        }
+        Err(error) => import_csv_error.set(Some(error)),
+    };
+
+    let comparison_totals = Memo::new(move |_| {
+        comparison.with(|loaded| {
+            loaded.as_ref().map(|(_, items)| {
+                (
+                    recipe_totals(items, &HashMap::new()),
+                    recipe_fiber(items, &HashMap::new()),
+                )
+            })
+        })
+    });
+
+    let load_comparison = move |_| match parse_share_url(&comparison_link.get_untracked()) {
+        Some(payload) => {
+            let state = recipe_from_payload(payload);
+            let name = if state.name.trim().is_empty() {
+                "Other recipe".to_string()
+            } else {
+                state.name
+            };
+            comparison.set(Some((name, state.ingredients)));
+            comparison_error.set(None);
+        }
+        None => {
+            comparison_error.set(Some(
+                "That doesn't look like a recipe link from this calculator.".to_string(),
+            ));
+        }
+    };
+
+    let total_servings = Memo::new(move |_| {
+        ingredients.with(|items| {
+            items
+                .iter()
+                .filter(|item| item.basis == Basis::PerServing)
+                .map(|item| item.multiplier(parse_quantity(&item.servings)))
+                .sum::<f64>()
+        })
+    });
+
+    // The headline ratio as a number, when there is one.
+    let headline_ratio = Memo::new(move |_| ratio_style().value(totals.get(), total_fiber.get()));
+
+    // (timestamp, ratio) samples for this session's sparkline, taken once
+    // edits settle. Not persisted, and separate from the undo history.
+    let ratio_history: RwSignal<Vec<(f64, f64)>> = RwSignal::new(Vec::new());
+    let ratio_sample = StoredValue::new(0_u32);
+    Effect::new(move || {
+        headline_ratio.track();
+        ratio_sample.update_value(|token| *token = token.wrapping_add(1));
+        let token = ratio_sample.get_value();
+        set_timeout(
+            move || {
+                if ratio_sample.get_value() != token {
+                    return;
+                }
+                let Some(ratio) = headline_ratio.get_untracked() else {
+                    return;
+                };
+                ratio_history.update(|samples| {
+                    if samples.last().is_some_and(|(_, last)| *last == ratio) {
+                        return;
+                    }
+                    samples.push((js_sys::Date::now(), ratio));
+                    if samples.len() > RATIO_HISTORY_LIMIT {
+                        samples.remove(0);
+                    }
+                });
+            },
+            RATIO_SAMPLE_DELAY,
+        );
+    });
+    // A new ratio kind isn't comparable with the earlier samples.
+    Effect::new(move |previous: Option<RatioMode>| {
+        let kind = ratio_mode.get();
+        if previous.is_some_and(|previous| previous != kind) {
+            ratio_history.update(Vec::clear);
+        }
+        kind
+    });
+
+    // Only known when every food with macros was entered by weight.
+    let total_weight = Memo::new(move |_| {
+        let overrides = servings_overrides.get();
+        ingredients.with(|items| {
+            let by_weight = items
+                .iter()
+                .filter(|item| item.basis.is_per_serving())
+                .all(|item| {
+                    recipe_totals(std::slice::from_ref(item), &overrides) == (0.0, 0.0, 0.0)
+                });
+            by_weight.then(|| {
+                items
+                    .iter()
+                    .filter(|item| !item.basis.is_per_serving())
+                    .map(|item| effective_servings(item, &overrides))
+                    .sum::<f64>()
+            })
+        })
+    });
+
+    let mean_ingredient_ratio = Memo::new(move |_| {
+        ingredients.with(|items| {
+            let ratios = items
+                .iter()
+                .filter_map(|item| {
+                    let servings = item.multiplier(parse_quantity(&item.servings));
+                    let energy =
+                        (parse_quantity(&item.fat) + parse_quantity(&item.net_carbs)) * servings;
+                    (energy > f64::MIN_POSITIVE)
+                        .then(|| parse_quantity(&item.protein) * servings / energy)
+                })
+                .collect::<Vec<_>>();
+            (!ratios.is_empty()).then(|| ratios.iter().sum::<f64>() / ratios.len() as f64)
+        })
+    });
+
+    let visible_ingredients = Memo::new(move |_| {
+        let query = search.get();
+        ingredients.with(|items| {
+            items
+                .iter()
+                .filter(|item| matches_search(&item.name, &query))
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+    });
+
+    Effect::new(move || {
+        let Some(root) = window()
+            .and_then(|win| win.document())
+            .and_then(|document| document.document_element())
+        else {
+            return;
+        };
+        let _ = if high_contrast.get() {
+            root.set_attribute("data-contrast", "more")
+        } else {
+            root.remove_attribute("data-contrast")
+        };
+    });
+
+    // Bands describe P:E, so they are only printed alongside that ratio.
+    let show_bands = move || print_bands.get() && ratio_mode.get() == RatioMode::Energy;
+
+    let filter_active = move || !search.with(|query| query.trim().is_empty());
+
+    let summary = view! {
+        <section
+            class="app__summary screen-only"
+            class=("app__summary--pinned", move || pin_totals.get())
+        >
+            <h2>
+                {move || {
+                    if filter_active() {
+                        format!("Totals (all {} ingredients)", ingredients.with(Vec::len))
+                    } else {
+                        "Totals".to_string()
+                    }
+                }}
+                <span
+                    class="live-indicator"
+                    class=("live-indicator--updated", move || totals_updated.get())
+                    title="Totals update as you type"
+                    aria-hidden="true"
+                ></span>
+            </h2>
+            <div class="units-toggle" role="group" aria-label="Show macro totals as">
+                <button
+                    class="ghost"
+                    class=("active", move || !summary_in_kcal.get())
+                    aria-pressed=move || (!summary_in_kcal.get()).to_string()
+                    on:click=move |_| summary_in_kcal.set(false)
+                >
+                    "Grams"
+                </button>
+                <button
+                    class="ghost"
+                    class=("active", move || summary_in_kcal.get())
+                    aria-pressed=move || summary_in_kcal.get().to_string()
+                    on:click=move |_| summary_in_kcal.set(true)
+                >
+                    "Calories"
+                </button>
+                <label class="pin-toggle">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || pin_totals.get()
+                        on:change=move |ev| pin_totals.set(event_target_checked(&ev))
+                    />
+                    <span>"Pin totals while scrolling"</span>
+                </label>
+                <label class="pin-toggle">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || group_totals.get()
+                        on:change=move |ev| group_totals.set(event_target_checked(&ev))
+                    />
+                    <span>"Group by food"</span>
+                </label>
+            </div>
+            {move || {
+                let count = incomplete_ids.with(HashSet::len);
+                (count > 0)
+                    .then(|| {
+                        let foods = if count == 1 {
+                            "1 food has".to_string()
+                        } else {
+                            format!("{count} foods have")
+                        };
+                        view! {
+                            <p class="totals-warning" role="status">
+                                {format!(
+                                    "Incomplete: {foods} blank macros, so these totals are estimates. Enter 0 where a food truly has none."
+                                )}
+                            </p>
+                        }
+                    })
+            }}
+            <ul>
+                <li>
+                    <span>Total protein</span>
+                    <strong>{approx}{
+                        move || {
+                            let (protein, _, _) = shown_totals.get();
+                            if summary_in_kcal.get() {
+                                format_kcal_with_target(protein, KCAL_PER_GRAM_PROTEIN, &target_protein.get(), rounding.get())
+                            } else {
+                                format_with_target(protein, &target_protein.get(), rounding.get())
+                            }
+                        }
+                    }</strong>
+                </li>
+                <li>
+                    <span>Total fat</span>
+                    <strong>{approx}{
+                        move || {
+                            let (_, fat, _) = shown_totals.get();
+                            if summary_in_kcal.get() {
+                                format_kcal_with_target(fat, KCAL_PER_GRAM_FAT, &target_fat.get(), rounding.get())
+                            } else {
+                                format_with_target(fat, &target_fat.get(), rounding.get())
+                            }
+                        }
+                    }</strong>
+                </li>
+                <Show when=move || sat_fat_total.get().is_some()>
+                    <li>
+                        <span>Total saturated fat</span>
+                        <strong>
+                            {move || {
+                                let sat_fat = sat_fat_total.get().unwrap_or_default();
+                                format!("{} g", format_number(sat_fat, rounding.get()))
+                            }}
+                        </strong>
+                    </li>
+                </Show>
+                <li>
+                    <span>Total net carbs</span>
+                    <strong>{approx}{
+                        move || {
+                            let (_, _, carbs) = shown_totals.get();
+                            if summary_in_kcal.get() {
+                                format_kcal_with_target(carbs, KCAL_PER_GRAM_NET_CARBS, &target_carbs.get(), rounding.get())
+                            } else {
+                                format_with_target(carbs, &target_carbs.get(), rounding.get())
+                            }
+                        }
+                    }</strong>
+                </li>
+                <li>
+                    <span>Total calories</span>
+                    <strong>{approx}{move || format_kcal(total_calories(shown_totals.get()), rounding.get())}</strong>
+                </li>
+                <li>
+                    <span>Calories by macro</span>
+                    <strong>{move || calorie_breakdown(totals.get(), rounding.get())}</strong>
+                </li>
+                <li>
+                    <span>Protein density</span>
+                    <strong>
+                        {move || {
+                            let totals = totals.get();
+                            protein_density(totals.0, total_calories(totals))
+                                .map(|density| format!("{} g per 100 kcal", format_number(density, rounding.get())))
+                                .unwrap_or_else(|| "—".to_string())
+                        }}
+                    </strong>
+                </li>
+                <li>
+                    <span>Total weight</span>
+                    <strong>
+                        {move || {
+                            total_weight
+                                .get()
+                                .map(|weight| format!("{} g", format_number(weight, rounding.get())))
+                                .unwrap_or_else(|| "— (enter every food by weight)".to_string())
+                        }}
+                    </strong>
+                </li>
+                <Show when=move || total_weight.get().is_some()>
+                    <li>
+                        <span title="Total weight minus protein, fat, net carbs and fiber. Much less than expected suggests a mistyped weight or macro.">
+                            "Other mass (water, ash, …)"
+                        </span>
+                        <strong>
+                            {move || {
+                                let (protein, fat, carbs) = totals.get();
+                                let macro_grams = protein + fat + carbs + total_fiber.get();
+                                let other = other_mass(total_weight.get().unwrap_or_default(), macro_grams);
+                                format!("{} g", format_number(other, rounding.get()))
+                            }}
+                        </strong>
+                    </li>
+                </Show>
+                <li>
+                    <span>Per 100 g of recipe</span>
+                    <strong>
+                        {move || {
+                            let (protein, fat, carbs) = totals.get();
+                            let weight = total_weight.get().unwrap_or_default();
+                            match (per_100g(protein, weight), per_100g(fat, weight), per_100g(carbs, weight)) {
+                                (Some(protein), Some(fat), Some(carbs)) => format!(
+                                    "Protein {} g / Fat {} g / Net carbs {} g",
+                                    format_number(protein, rounding.get()),
+                                    format_number(fat, rounding.get()),
+                                    format_number(carbs, rounding.get()),
+                                ),
+                                _ => "—".to_string(),
+                            }
+                        }}
+                    </strong>
+                </li>
+                <li>
+                    <span>Total servings</span>
+                    <strong>{move || format_number(total_servings.get(), rounding.get())}</strong>
+                </li>
+                <li class="highlight">
+                    <span>
+                        {move || format!("{} ratio ", ratio_mode.get().label())}
+                        <Show when=move || ratio_mode.get() == RatioMode::Energy>
+                            <span
+                                class="info-tip"
+                                tabindex="0"
+                                title="Total protein ÷ (total fat + total net carbs). Because it is a ratio of totals, ingredients contributing more grams weigh more heavily, so it usually differs from the simple average of the ingredient ratios."
+                            >
+                                "?"
+                            </span>
+                        </Show>
+                    </span>
+                    <strong>
+                        {approx}
+                        {move || {
+                            shown_ratio(totals.get(), total_fiber.get())
+                        }}
+                        <Show when=move || fiber_in_ratio.get()>
+                            <span class="ratio-note">" incl. fiber"</span>
+                        </Show>
+                    </strong>
+                </li>
+                <Show when=move || ratio_history.with(|samples| samples.len() >= 2)>
+                    <li>
+                        <span>{move || format!("{} ratio this session", ratio_mode.get().label())}</span>
+                        <svg
+                            class="sparkline"
+                            viewBox="0 0 100 24"
+                            preserveAspectRatio="none"
+                            role="img"
+                            aria-label=move || {
+                                ratio_history.with(|samples| {
+                                    let first = samples.first().map(|(_, ratio)| *ratio).unwrap_or_default();
+                                    let last = samples.last().map(|(_, ratio)| *ratio).unwrap_or_default();
+                                    format!(
+                                        "From {} to {} over {} changes",
+                                        format_number(first, rounding.get()),
+                                        format_number(last, rounding.get()),
+                                        samples.len() - 1,
+                                    )
+                                })
+                            }
+                        >
+                            <polyline points=move || ratio_history.with(|samples| sparkline_points(samples))></polyline>
+                        </svg>
+                    </li>
+                </Show>
+                <li>
+                    <span>
+                        "Diet fit for "
+                        <input
+                            class="number-input"
+                            type="text"
+                            inputmode="decimal"
+                            aria-label="Portions"
+                            prop:value=move || portions.get()
+                            on:change=move |ev| portions.set(event_target_value(&ev))
+                        />
+                        " portions"
+                    </span>
+                    <strong>
+                        {move || {
+                            let totals = totals.get();
+                            let portions_count = parse_quantity(&portions.get());
+                            let thresholds = DietThresholds {
+                                keto_max_carbs: keto_max_carbs.get(),
+                                high_carb_min: high_carb_min.get(),
+                            };
+                            classify_recipe(totals, portions_count, thresholds)
+                                .map(|class| {
+                                    let reason = format!(
+                                        "{} g net carbs per portion (keto-friendly at or below {} g and with no more net carbs than fat or protein, high-carb above {} g); {} {}",
+                                        format_number(totals.2 / portions_count, rounding.get()),
+                                        thresholds.keto_max_carbs,
+                                        thresholds.high_carb_min,
+                                        ratio_mode.get().label(),
+                                        shown_ratio(totals, total_fiber.get()),
+                                    );
+                                    view! {
+                                        <span
+                                            class=format!("diet-badge diet-badge--{}", class.label().to_lowercase())
+                                            tabindex="0"
+                                            title=reason
+                                        >
+                                            {class.label()}
+                                        </span>
+                                    }
+                                })
+                        }}
+                    </strong>
+                </li>
+                <li>
+                    <span>Average of ingredient P:E ratios (unweighted)</span>
+                    <strong>
+                        {move || {
+                            mean_ingredient_ratio
+                                .get()
+                                .map(|ratio| format_number(ratio, rounding.get()))
+                                .unwrap_or_else(|| "—".to_string())
+                        }}
+                    </strong>
+                </li>
+            </ul>
+            <Show when=move || group_totals.get()>
+                <table class="compare-table grouped-totals">
+                    <thead>
+                        <tr>
+                            <th>"Food"</th>
+                            <th>"Protein"</th>
+                            <th>"Fat"</th>
+                            <th>"Net carbs"</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {move || {
+                            let mode = rounding.get();
+                            grouped_totals
+                                .get()
+                                .into_iter()
+                                .map(|(name, (protein, fat, carbs))| {
+                                    view! {
+                                        <tr>
+                                            <td>{name}</td>
+                                            <td>{format!("{} g", format_number(protein, mode))}</td>
+                                            <td>{format!("{} g", format_number(fat, mode))}</td>
+                                            <td>{format!("{} g", format_number(carbs, mode))}</td>
+                                        </tr>
+                                    }
+                                })
+                                .collect_view()
+                        }}
+                    </tbody>
+                </table>
+            </Show>
+        </section>
+    };
     let stylesheet = include_str!("./styles.css");
 
+    if embed {
+        return view! {
+            <style>{stylesheet}</style>
+            <main class="app app--embed">{summary}</main>
+        }
+        .into_any();
+    }
+
     view! {
         <style>{stylesheet}</style>
         <main class="app">
+            <Show when=move || invalid_link.get()>
+                <div class="link-banner screen-only" role="alert">
+                    <p>
+                        "This link is invalid or from a newer version of the calculator, so a blank recipe was opened instead."
+                    </p>
+                    <button class="ghost" on:click=move |_| invalid_link.set(false)>
+                        "Dismiss"
+                    </button>
+                </div>
+            </Show>
+            <Show when=move || storage_full.get()>
+                <div class="storage-banner screen-only" role="alert">
+                    <p>
+                        {format!(
+                            "Your browser's storage for this site is full (about {} KB used), so some settings weren't saved. Clear this site's saved data in your browser settings to free space.",
+                            storage_usage_bytes().div_ceil(1024)
+                        )}
+                    </p>
+                    <button class="ghost" on:click=move |_| storage_full.set(false)>
+                        "Dismiss"
+                    </button>
+                </div>
+            </Show>
             <section class="app__header screen-only">
-                <h1>"P:E Diet Recipe Calculator"</h1>
-                <p>
-                    "The "
-                    <a href="https://thepediet.com/" target="_blank">"P:E Diet"</a>
-                    " focuses on maximizing protein and reducing energy (fat and net carbs). "
-                    "This site provides a convenient way to calculate these ratios."
-                </p>
-                <p>
-                    "Build a recipe from food labels, enter their per-serving macros, "
-                    "and specify how many servings of each item you plan to use. "
-                    "The calculator totals protein, fat, and net carbs, and "
-                    "shows the overall protein efficiency ratio (protein ÷ fat+net carbs)."
-                </p>
-                <p>
-                    "Provided by "
-                    <a href="https://www.snoyman.com/" target="_blank">Michael Snoyman</a>
-                    ". This project is open source, code is available at "
-                    <a href="https://github.com/snoyberg/pedietcalc" target="_blank">
-                        <code>"github:snoyberg/pedietcalc"</code>
-                    </a>
-                    "."
-                </p>
+                <div class="app__title">
+                    <h1>"P:E Diet Recipe Calculator"</h1>
+                    <button
+                        class="ghost"
+                        aria-pressed=move || focus_mode.get().to_string()
+                        on:click=move |_| focus_mode.update(|focus| *focus = !*focus)
+                    >
+                        {move || if focus_mode.get() { "Show intro" } else { "Hide intro" }}
+                    </button>
+                </div>
+                <Show when=move || !focus_mode.get()>
+                    <p>
+                        "The "
+                        <a href="https://thepediet.com/" target="_blank">"P:E Diet"</a>
+                        " focuses on maximizing protein and reducing energy (fat and net carbs). "
+                        "This site provides a convenient way to calculate these ratios."
+                    </p>
+                    <p>
+                        "Build a recipe from food labels, enter their per-serving macros, "
+                        "and specify how many servings of each item you plan to use. "
+                        "The calculator totals protein, fat, and net carbs, and "
+                        "shows the overall protein efficiency ratio (protein ÷ fat+net carbs)."
+                    </p>
+                    <p>
+                        "Provided by "
+                        <a href="https://www.snoyman.com/" target="_blank">Michael Snoyman</a>
+                        ". This project is open source, code is available at "
+                        <a href="https://github.com/snoyberg/pedietcalc" target="_blank">
+                            <code>"github:snoyberg/pedietcalc"</code>
+                        </a>
+                        "."
+                    </p>
+                </Show>
+                <details
+                    class="help-panel"
+                    prop:open=move || help_open.get()
+                    on:toggle=move |ev| {
+                        if let Some(details) = ev
+                            .target()
+                            .and_then(|target| target.dyn_into::<HtmlDetailsElement>().ok())
+                        {
+                            help_open.set(details.open());
+                        }
+                    }
+                >
+                    <summary>"How is the P:E ratio calculated?"</summary>
+                    <p>
+                        "P:E ratio = protein ÷ (fat + net carbs), using grams. "
+                        "Net carbs are total carbohydrates minus fiber. "
+                        "A higher ratio means more protein for the energy you eat."
+                    </p>
+                    <p>
+                        "Example: an ingredient with 30 g protein, 10 g fat, and 5 g net carbs "
+                        "has a ratio of 30 ÷ (10 + 5) = 2.00. "
+                        "For a whole recipe, each total is summed across all ingredients "
+                        "(per-serving values × servings used) before dividing."
+                    </p>
+                </details>
                 <label class="recipe-name-field">
                     <span>"Recipe name (optional)"</span>
                     <input
@@ -177,30 +1955,310 @@ pub fn App() -> impl IntoView {
                         type="text"
                         placeholder="e.g. High-protein chili"
                         prop:value=move || recipe_name.get()
+                        readonly=move || read_only.get()
                         on:input=move |ev| {
                             set_recipe_name.set(event_target_value(&ev));
                         }
                     />
                 </label>
+                <label class="recipe-name-field">
+                    <span>"Notes (optional, printed with the recipe)"</span>
+                    <textarea
+                        class="recipe-notes-input"
+                        rows="3"
+                        placeholder="Cooking instructions, substitutions, …"
+                        prop:value=move || notes.get()
+                        readonly=move || read_only.get()
+                        on:input=move |ev| notes.set(event_target_value(&ev))
+                    ></textarea>
+                </label>
                 </section>
 
                 <section class="app__actions screen-only">
+                    <Show when=move || read_only.get()>
+                        <p class="view-mode-note">
+                            "You are viewing a shared recipe. Make a copy to change it."
+                        </p>
+                    </Show>
                     <div class="button-row">
-                        <button class="primary" on:click=add_ingredient>
-                            "+ Add food"
-                        </button>
+                        <Show
+                            when=move || read_only.get()
+                            fallback=move || {
+                                view! {
+                                    <button class="primary" on:click=add_ingredient>
+                                        "+ Add food"
+                                    </button>
+                                    <button class="secondary" on:click=make_copy>
+                                        "Make a copy"
+                                    </button>
+                                    <button class="secondary" on:click=merge_duplicate_ingredients>
+                                        "Merge duplicates"
+                                    </button>
+                                    <button class="secondary" on:click=new_from_template>
+                                        "New from template"
+                                    </button>
+                                }
+                            }
+                        >
+                            <button class="primary" on:click=make_copy>
+                                "Make a copy to edit"
+                            </button>
+                        </Show>
                         <button class="secondary" on:click=print_recipe>
                             "Print recipe"
                         </button>
+                        <button class="secondary" on:click=download_pdf>
+                            "Download PDF"
+                        </button>
+                        <button class="secondary" on:click=copy_markdown>
+                            "Copy as Markdown"
+                        </button>
+                        <button class="secondary" on:click=copy_summary>
+                            "Copy summary"
+                        </button>
+                        <Show when=move || differs_from_original.get()>
+                            <button class="secondary" on:click=revert_to_original>
+                                "Revert to shared version"
+                            </button>
+                        </Show>
                     </div>
+                    <Show when=move || pdf_hint.get()>
+                        <p class="action-message">
+                            "To save a PDF, choose “Save as PDF” as the destination in the print dialog."
+                        </p>
+                    </Show>
+                    {move || {
+                        copy_message
+                            .get()
+                            .map(|message| view! { <p class="action-message">{message}</p> })
+                    }}
+                    {move || {
+                        merge_message
+                            .get()
+                            .map(|message| view! { <p class="action-message">{message}</p> })
+                    }}
+                    <Show when=move || SHORTENER_ENDPOINT.is_some()>
+                        <div class="short-link">
+                            <button
+                                class="secondary"
+                                disabled=move || short_link_pending.get()
+                                on:click=get_short_link
+                            >
+                                {move || if short_link_pending.get() { "Shortening…" } else { "Get short link" }}
+                            </button>
+                            {move || match short_link.get() {
+                                Some(Ok(url)) => view! {
+                                    <input class="text-input" type="text" readonly prop:value=url />
+                                }
+                                .into_any(),
+                                Some(Err(error)) => view! { <p class="field-error">{error}</p> }.into_any(),
+                                None => ().into_any(),
+                            }}
+                        </div>
+                    </Show>
+                    {move || {
+                        share_url_length
+                            .get()
+                            .map(|length| {
+                                let status = share_url_status(length);
+                                view! {
+                                    <p class=format!("share-link-size share-link-size--{status}")>
+                                        {format!("Share link: {length} / {SHARE_URL_LIMIT} characters")}
+                                        {(status != "ok")
+                                            .then_some(" — long links may break when shared")}
+                                    </p>
+                                }
+                            })
+                    }}
+                    <Show when=move || !auto_link.get()>
+                        <div class="tool-row">
+                            <button
+                                class="secondary"
+                                disabled=move || encoded_recipe.with(|encoded| linked_recipe.with(|linked| linked == encoded))
+                                on:click=move |_| link_update.update(|count| *count = count.wrapping_add(1))
+                            >
+                                "Update link"
+                            </button>
+                            <span class="tool-preview">"The address bar keeps the last saved version until you update it."</span>
+                        </div>
+                    </Show>
+                    <Show when=move || !read_only.get()>
+                        <label class="setting-field">
+                            <input
+                                type="checkbox"
+                                prop:checked=move || recipe_locked.get()
+                                on:change=move |ev| recipe_locked.set(event_target_checked(&ev))
+                            />
+                            <span>"Open the share link read-only (recipients can still make a copy to edit)"</span>
+                        </label>
+                    </Show>
                 </section>
 
-            <section class="app__ingredients screen-only">
+            <section class="app__filter screen-only">
+                <div class="filter-row">
+                    <input
+                        class="text-input"
+                        type="search"
+                        placeholder="Filter ingredients by name"
+                        prop:value=move || search.get()
+                        on:input=move |ev| search.set(event_target_value(&ev))
+                    />
+                    <button
+                        class="ghost"
+                        on:click=move |_| {
+                            collapsed.set(ingredients.with(|items| items.iter().map(|item| item.id).collect()));
+                        }
+                    >
+                        "Collapse all"
+                    </button>
+                    <button class="ghost" on:click=move |_| collapsed.update(HashSet::clear)>
+                        "Expand all"
+                    </button>
+                    <button
+                        class="ghost"
+                        aria-pressed=move || compact_view.get().to_string()
+                        on:click=move |_| compact_view.update(|compact| *compact = !*compact)
+                    >
+                        {move || if compact_view.get() { "Card view" } else { "Table view" }}
+                    </button>
+                </div>
+                <Show when=move || !read_only.get()>
+                    <div class="filter-row">
+                        <button
+                            class="ghost"
+                            on:click=move |_| {
+                                selected.set(visible_ingredients.with(|items| items.iter().map(|item| item.id).collect()));
+                            }
+                        >
+                            "Select all"
+                        </button>
+                        <button
+                            class="ghost"
+                            disabled=move || selected.with(HashSet::is_empty)
+                            on:click=move |_| selected.update(HashSet::clear)
+                        >
+                            "Clear selection"
+                        </button>
+                        <button
+                            class="ghost danger"
+                            disabled=move || selected.with(HashSet::is_empty)
+                            on:click=delete_selected
+                        >
+                            {move || format!("Delete selected ({})", selected.with(HashSet::len))}
+                        </button>
+                    </div>
+                </Show>
+                <Show when=filter_active>
+                    <p class="filter-note">
+                        {move || {
+                            format!(
+                                "Showing {} of {} ingredients. Totals still include every ingredient.",
+                                visible_ingredients.with(Vec::len),
+                                ingredients.with(Vec::len),
+                            )
+                        }}
+                    </p>
+                </Show>
+            </section>
+
+            <datalist id="recent-foods">
+                {move || {
+                    recent_foods
+                        .get()
+                        .into_iter()
+                        .map(|food| view! { <option value=food.name></option> })
+                        .collect_view()
+                }}
+            </datalist>
+            <section class="app__ingredients screen-only" hidden=move || compact_view.get()>
                 <For
-                    each=move || ingredients.get()
+                    each=move || visible_ingredients.get()
                     key=|ingredient: &Ingredient| ingredient.id
                     children=move |ingredient: Ingredient| {
                         let id = ingredient.id;
+                            let basis = move || {
+                                ingredients.with(|items| {
+                                    items
+                                        .iter()
+                                        .find(|item| item.id == id)
+                                        .map(|item| item.basis)
+                                        .unwrap_or_default()
+                                })
+                            };
+                            let label_text = move |text: fn(&Ingredient) -> String| {
+                                move || {
+                                    ingredients.with(|items| {
+                                        items
+                                            .iter()
+                                            .find(|item| item.id == id)
+                                            .map(text)
+                                            .unwrap_or_default()
+                                    })
+                                }
+                            };
+                            let macro_label = label_text(Ingredient::macro_label);
+                            let amount_suffix = label_text(Ingredient::amount_suffix);
+                            let is_locked = move || {
+                                ingredients.with(|items| {
+                                    items.iter().any(|item| item.id == id && item.locked)
+                                })
+                            };
+                            // One of the label fields that net carbs are worked out from.
+                            let carb_field = move |
+                                label: &'static str,
+                                placeholder: &'static str,
+                                get: fn(&Ingredient) -> &String,
+                                set: fn(&mut Ingredient, String),
+                            | {
+                                macro_input(
+                                    move || format!("{label} (g {})", macro_label()),
+                                    placeholder,
+                                    "g",
+                                    move || {
+                                        ingredients.with(|items| {
+                                            items
+                                                .iter()
+                                                .find(|item| item.id == id)
+                                                .map(|item| get(item).clone())
+                                                .unwrap_or_default()
+                                        })
+                                    },
+                                    move |value| {
+                                        let factor = sugar_alcohol_factor.get_untracked();
+                                        update_ingredient(set_ingredients, id, |item| {
+                                            set(item, value);
+                                            item.apply_carb_breakdown(factor);
+                                        });
+                                    },
+                                    read_only.into(),
+                                    numbers_only.into(),
+                                )
+                            };
+                            // Touch position where a swipe started, and how far
+                            // left the card has been dragged since.
+                            let swipe_start: RwSignal<Option<(f64, f64)>> = RwSignal::new(None);
+                            let swipe_offset = RwSignal::new(0.0_f64);
+                            let note = move || {
+                                ingredients.with(|items| {
+                                    items
+                                        .iter()
+                                        .find(|item| item.id == id)
+                                        .map(|item| item.note.clone())
+                                        .unwrap_or_default()
+                                })
+                            };
+                            let exceeds_weight = Memo::new(move |_| {
+                                ingredients.with(|items| {
+                                    items.iter().find(|item| item.id == id).is_some_and(|item| {
+                                        macros_exceed_weight(
+                                            item.basis,
+                                            parse_quantity(&item.protein),
+                                            parse_quantity(&item.fat),
+                                            parse_quantity(&item.net_carbs),
+                                        )
+                                    })
+                                })
+                            });
                             let per_recipe_protein = {
                                 let ingredients = ingredients;
                                 move || {
@@ -208,7 +2266,10 @@ pub fn App() -> impl IntoView {
                                         items
                                             .iter()
                                             .find(|item| item.id == id)
-                                            .map(|item| parse_quantity(&item.protein) * parse_quantity(&item.servings))
+                                            .map(|item| {
+                                                parse_quantity(&item.protein)
+                                                    * servings_overrides.with(|overrides| effective_multiplier(item, overrides))
+                                            })
                                             .unwrap_or_default()
                                     })
                                 }
@@ -220,11 +2281,26 @@ pub fn App() -> impl IntoView {
                                         items
                                             .iter()
                                             .find(|item| item.id == id)
-                                            .map(|item| parse_quantity(&item.fat) * parse_quantity(&item.servings))
+                                            .map(|item| {
+                                                parse_quantity(&item.fat)
+                                                    * servings_overrides.with(|overrides| effective_multiplier(item, overrides))
+                                            })
                                             .unwrap_or_default()
                                     })
                                 }
                             };
+                            let per_recipe_fiber = move || {
+                                ingredients.with(|items| {
+                                    items
+                                        .iter()
+                                        .find(|item| item.id == id)
+                                        .map(|item| {
+                                            parse_quantity(&item.fiber)
+                                                * servings_overrides.with(|overrides| effective_multiplier(item, overrides))
+                                        })
+                                        .unwrap_or_default()
+                                })
+                            };
                             let per_recipe_carbs = {
                                 let ingredients = ingredients;
                                 move || {
@@ -232,19 +2308,111 @@ pub fn App() -> impl IntoView {
                                         items
                                             .iter()
                                             .find(|item| item.id == id)
-                                            .map(|item| parse_quantity(&item.net_carbs) * parse_quantity(&item.servings))
+                                            .map(|item| {
+                                                parse_quantity(&item.net_carbs)
+                                                    * servings_overrides.with(|overrides| effective_multiplier(item, overrides))
+                                            })
                                             .unwrap_or_default()
                                     })
                                 }
                             };
+                            // This card's share of each recipe total, in percent.
+                            let contribution = Memo::new(move |_| {
+                                let (protein, fat, carbs) = totals.get();
+                                [
+                                    percent_of(per_recipe_protein(), protein),
+                                    percent_of(per_recipe_fat(), fat),
+                                    percent_of(per_recipe_carbs(), carbs),
+                                ]
+                            });
 
                         view! {
-                            <article class="ingredient-card">
+                            <article
+                                class="ingredient-card"
+                                class=("card--collapsed", move || collapsed.with(|ids| ids.contains(&id)))
+                                class=("card--swiping", move || swipe_start.get().is_some())
+                                on:focusout=move |ev| {
+                                    // Remember the food once focus leaves the card, after
+                                    // its macros have been entered, not on leaving the name.
+                                    let card = ev
+                                        .current_target()
+                                        .and_then(|target| target.dyn_into::<web_sys::Node>().ok());
+                                    let next = ev
+                                        .related_target()
+                                        .and_then(|target| target.dyn_into::<web_sys::Node>().ok());
+                                    if card.is_some_and(|card| card.contains(next.as_ref())) {
+                                        return;
+                                    }
+                                    let item = ingredients
+                                        .with_untracked(|items| items.iter().find(|item| item.id == id).cloned());
+                                    if let Some(item) = item {
+                                        remember_food(&item);
+                                    }
+                                }
+                                style:transform=move || {
+                                    let offset = swipe_offset.get();
+                                    (offset != 0.0).then(|| format!("translateX({offset}px)"))
+                                }
+                                on:pointerdown=move |ev| {
+                                    if ev.pointer_type() == "touch" && !read_only.get_untracked() && !is_text_entry(&ev) {
+                                        swipe_start.set(Some((f64::from(ev.client_x()), f64::from(ev.client_y()))));
+                                    }
+                                }
+                                on:pointermove=move |ev| {
+                                    let Some((start_x, start_y)) = swipe_start.get_untracked() else {
+                                        return;
+                                    };
+                                    let dx = f64::from(ev.client_x()) - start_x;
+                                    let dy = f64::from(ev.client_y()) - start_y;
+                                    if dy.abs() > dx.abs() && swipe_offset.get_untracked() == 0.0 {
+                                        // A vertical drag is a scroll, not a swipe.
+                                        swipe_start.set(None);
+                                    } else {
+                                        swipe_offset.set(dx.min(0.0));
+                                    }
+                                }
+                                on:pointerup=move |_| {
+                                    let swiped = swipe_offset.get_untracked() <= -SWIPE_REMOVE_DISTANCE;
+                                    swipe_start.set(None);
+                                    swipe_offset.set(0.0);
+                                    if swiped {
+                                        remove_ingredient(id);
+                                    }
+                                }
+                                on:pointercancel=move |_| {
+                                    swipe_start.set(None);
+                                    swipe_offset.set(0.0);
+                                }
+                            >
                                 <div class="card__header">
+                                    <Show when=move || !read_only.get()>
+                                        <input
+                                            type="checkbox"
+                                            class="card__select"
+                                            aria-label="Select ingredient"
+                                            prop:checked=move || selected.with(|ids| ids.contains(&id))
+                                            on:change=move |ev| {
+                                                let checked = event_target_checked(&ev);
+                                                selected.update(|ids| {
+                                                    if checked {
+                                                        ids.insert(id);
+                                                    } else {
+                                                        ids.remove(&id);
+                                                    }
+                                                });
+                                            }
+                                        />
+                                    </Show>
                                     <input
                                         class="text-input"
                                         type="text"
                                         placeholder="Ingredient name"
+                                        list="recent-foods"
+                                        title=move || {
+                                            let note = note();
+                                            (!note.is_empty()).then_some(note)
+                                        }
+                                        readonly=move || read_only.get()
                                         prop:value=move || {
                                             ingredients.with(|items| {
                                                 items
@@ -256,21 +2424,171 @@ pub fn App() -> impl IntoView {
                                         }
                                         on:input=move |ev| {
                                             let value = event_target_value(&ev);
-                                            update_ingredient(set_ingredients, id, |item| item.name = value);
+                                            // Only picking a suggestion or pasting a name fills
+                                            // in its macros; typing or deleting never does.
+                                            // Some browsers report a pick as a plain `Event`.
+                                            let picked = ev
+                                                .dyn_ref::<web_sys::InputEvent>()
+                                                .is_none_or(|ev| {
+                                                    matches!(
+                                                        ev.input_type().as_str(),
+                                                        "insertReplacementText" | "insertFromPaste"
+                                                    )
+                                                });
+                                            let recent = picked
+                                                .then(|| {
+                                                    recent_foods.with_untracked(|foods| {
+                                                        foods
+                                                            .iter()
+                                                            .find(|food| food.name.eq_ignore_ascii_case(value.trim()))
+                                                            .cloned()
+                                                    })
+                                                })
+                                                .flatten();
+                                            update_ingredient(set_ingredients, id, |item| {
+                                                item.name = value;
+                                                if let Some(food) = recent {
+                                                    item.protein = food.protein;
+                                                    item.fat = food.fat;
+                                                    item.net_carbs = food.net_carbs;
+                                                    item.sat_fat = food.sat_fat;
+                                                    item.total_carbs = food.total_carbs;
+                                                    item.fiber = food.fiber;
+                                                    item.sugar_alcohols = food.sugar_alcohols;
+                                                    item.basis = food.basis;
+                                                    item.base_amount = food.base_amount;
+                                                    item.unit = food.unit;
+                                                    item.servings_per_container = food.servings_per_container;
+                                                }
+                                            });
                                         }
                                     />
+                                    <Show when=move || !read_only.get()>
+                                        <button class="ghost" on:click=move |_| editing.set(Some(id))>
+                                            "Edit"
+                                        </button>
+                                    </Show>
+                                    <button
+                                        class="ghost card__lock"
+                                        class=("card__lock--on", is_locked)
+                                        disabled=move || read_only.get()
+                                        aria-pressed=move || is_locked().to_string()
+                                        title=move || {
+                                            if is_locked() {
+                                                "Locked: batch tools leave these servings alone"
+                                            } else {
+                                                "Lock servings against batch tools"
+                                            }
+                                        }
+                                        on:click=move |_| {
+                                            update_ingredient(set_ingredients, id, |item| item.locked = !item.locked);
+                                        }
+                                    >
+                                        {move || if is_locked() { "🔒" } else { "🔓" }}
+                                    </button>
                                     <button
                                         class="ghost"
-                                        disabled=move || ingredients.with(|items| items.len() <= 1)
-                                        on:click=move |_| remove_ingredient(id)
+                                        on:click=move |_| {
+                                            collapsed.update(|ids| {
+                                                if !ids.remove(&id) {
+                                                    ids.insert(id);
+                                                }
+                                            });
+                                        }
                                     >
-                                        "Remove"
+                                        {move || {
+                                            if collapsed.with(|ids| ids.contains(&id)) {
+                                                "Expand"
+                                            } else {
+                                                "Collapse"
+                                            }
+                                        }}
                                     </button>
+                                    <Show when=move || !read_only.get()>
+                                        // Out of the Tab order by default so tabbing from one
+                                        // card to the next can't land on it by accident.
+                                        <button
+                                            class="ghost"
+                                            tabindex=move || if tab_skips_remove.get() { "-1" } else { "0" }
+                                            disabled=move || ingredients.with(|items| items.len() <= 1)
+                                            on:click=move |_| remove_ingredient(id)
+                                        >
+                                            "Remove"
+                                        </button>
+                                    </Show>
                                 </div>
 
+                                <Show when=move || !read_only.get() || !note().is_empty()>
+                                    <details class="card__note" open=!ingredient.note.trim().is_empty()>
+                                        <summary>"Note"</summary>
+                                        <input
+                                            class="text-input"
+                                            type="text"
+                                            placeholder="e.g. drained weight, raw"
+                                            readonly=move || read_only.get()
+                                            prop:value=note
+                                            on:input=move |ev| {
+                                                let value = event_target_value(&ev);
+                                                update_ingredient(set_ingredients, id, |item| item.note = value);
+                                            }
+                                        />
+                                    </details>
+                                </Show>
+
+                                <p class="card__collapsed-summary">
+                                    {move || {
+                                        let name = ingredients.with(|items| {
+                                            items
+                                                .iter()
+                                                .find(|item| item.id == id)
+                                                .map(|item| item.name.trim().to_string())
+                                                .unwrap_or_default()
+                                        });
+                                        let protein = per_recipe_protein();
+                                        let kind = ratio_mode.get();
+                                        let energy = with_fiber((protein, per_recipe_fat(), per_recipe_carbs()), ratio_fiber(per_recipe_fiber()));
+                                        let ratio = format_ratio_as(kind, energy, rounding.get(), &unbounded_ratio());
+                                        format!(
+                                            "{} · Protein {} g · {} {}",
+                                            if name.is_empty() { "Unnamed ingredient" } else { &name },
+                                            format_number(protein, rounding.get()),
+                                            kind.label(),
+                                            ratio,
+                                        )
+                                    }}
+                                </p>
+
                                 <div class="card__grid">
+                                    <label class="card__field">
+                                        <span>"Macros are given"</span>
+                                        <select
+                                            class="number-input"
+                                            disabled=move || read_only.get()
+                                            on:change=move |ev| {
+                                                if let Ok(new_basis) = event_target_value(&ev).parse() {
+                                                    update_ingredient(set_ingredients, id, |item| item.basis = new_basis);
+                                                }
+                                            }
+                                        >
+                                            {Basis::ALL
+                                                .into_iter()
+                                                .map(|option| {
+                                                    view! {
+                                                        <option
+                                                            value=option.to_string()
+                                                            selected=move || basis() == option
+                                                        >
+                                                            {option.macro_label()}
+                                                        </option>
+                                                    }
+                                                })
+                                                .collect_view()}
+                                        </select>
+                                    </label>
                                     {macro_input(
-                                        "Protein (g per serving)",
+                                        move || format!("Protein (g {})", macro_label()),
+                                        "e.g. 20",
+                                        "g",
                                             {
                                                 move || {
                                                     ingredients.with(|items| {
@@ -285,9 +2603,13 @@ pub fn App() -> impl IntoView {
                                             move |value| {
                                                 update_ingredient(set_ingredients, id, |item| item.protein = value);
                                             },
+                                            read_only.into(),
+                                            numbers_only.into(),
                                         )}
                                         {macro_input(
-                                            "Fat (g per serving)",
+                                            move || format!("Fat (g {})", macro_label()),
+                                            "e.g. 5",
+                                            "g",
                                             {
                                                 let ingredients = ingredients;
                                                 move || {
@@ -303,9 +2625,13 @@ pub fn App() -> impl IntoView {
                                             move |value| {
                                                 update_ingredient(set_ingredients, id, |item| item.fat = value);
                                             },
+                                            read_only.into(),
+                                            numbers_only.into(),
                                         )}
                                         {macro_input(
-                                            "Net carbs (g per serving)",
+                                            move || format!("Net carbs (g {})", macro_label()),
+                                            "e.g. 3",
+                                            "g",
                                             {
                                                 let ingredients = ingredients;
                                                 move || {
@@ -321,9 +2647,35 @@ pub fn App() -> impl IntoView {
                                             move |value| {
                                                 update_ingredient(set_ingredients, id, |item| item.net_carbs = value);
                                             },
+                                            read_only.into(),
+                                            numbers_only.into(),
                                         )}
                                         {macro_input(
-                                            "Servings used in recipe",
+                                            move || format!("Saturated fat (g {}, optional)", macro_label()),
+                                            "e.g. 2",
+                                            "g",
+                                            {
+                                                let ingredients = ingredients;
+                                                move || {
+                                                    ingredients.with(|items| {
+                                                        items
+                                                            .iter()
+                                                            .find(|item| item.id == id)
+                                                            .map(|item| item.sat_fat.clone())
+                                                            .unwrap_or_default()
+                                                    })
+                                                }
+                                            },
+                                            move |value| {
+                                                update_ingredient(set_ingredients, id, |item| item.sat_fat = value);
+                                            },
+                                            read_only.into(),
+                                            numbers_only.into(),
+                                        )}
+                                        {macro_input_with_warning(
+                                            label_text(Ingredient::amount_label),
+                                            "e.g. 1",
+                                            label_text(Ingredient::amount_suffix),
                                             {
                                                 let ingredients = ingredients;
                                                 move || {
@@ -339,19 +2691,248 @@ pub fn App() -> impl IntoView {
                                             move |value| {
                                                 update_ingredient(set_ingredients, id, |item| item.servings = value);
                                             },
+                                            read_only.into(),
+                                            numbers_only.into(),
+                                            zero_amount_warning(ingredients, id),
                                         )}
                                     </div>
 
+                                    <details class="card__carbs">
+                                        <summary>"Work out net carbs from a label"</summary>
+                                        <div class="card__grid">
+                                            {carb_field("Total carbs", "e.g. 12", |item| &item.total_carbs, |item, value| item.total_carbs = value)}
+                                            {carb_field("Fiber", "e.g. 4", |item| &item.fiber, |item, value| item.fiber = value)}
+                                            {carb_field("Sugar alcohols", "e.g. 6", |item| &item.sugar_alcohols, |item, value| item.sugar_alcohols = value)}
+                                        </div>
+                                    </details>
+
+                                    <Show when=move || basis().is_per_serving()>
+                                        <details
+                                            class="card__carbs"
+                                            open=!ingredient.base_amount.trim().is_empty()
+                                        >
+                                            <summary>"Label serving is a custom amount"</summary>
+                                            <div class="card__grid">
+                                                {macro_input(
+                                                    "Label serving (e.g. 3 for \"per 3 crackers\")",
+                                                    "e.g. 3",
+                                                    move || {
+                                                        ingredients.with(|items| {
+                                                            items
+                                                                .iter()
+                                                                .find(|item| item.id == id)
+                                                                .map(|item| item.unit.clone())
+                                                                .unwrap_or_default()
+                                                        })
+                                                    },
+                                                    move || {
+                                                        ingredients.with(|items| {
+                                                            items
+                                                                .iter()
+                                                                .find(|item| item.id == id)
+                                                                .map(|item| item.base_amount.clone())
+                                                                .unwrap_or_default()
+                                                        })
+                                                    },
+                                                    move |value| {
+                                                        update_ingredient(set_ingredients, id, |item| item.base_amount = value);
+                                                    },
+                                                    read_only.into(),
+                                                    numbers_only.into(),
+                                                )}
+                                                <label class="card__field">
+                                                    <span>"Unit"</span>
+                                                    <input
+                                                        class="text-input"
+                                                        type="text"
+                                                        placeholder="e.g. crackers"
+                                                        readonly=move || read_only.get()
+                                                        prop:value=move || {
+                                                            ingredients.with(|items| {
+                                                                items
+                                                                    .iter()
+                                                                    .find(|item| item.id == id)
+                                                                    .map(|item| item.unit.clone())
+                                                                    .unwrap_or_default()
+                                                            })
+                                                        }
+                                                        on:input=move |ev| {
+                                                            let value = event_target_value(&ev);
+                                                            update_ingredient(set_ingredients, id, |item| item.unit = value);
+                                                        }
+                                                    />
+                                                </label>
+                                            </div>
+                                        </details>
+                                        <details
+                                            class="card__carbs"
+                                            open=!ingredient.servings_per_container.trim().is_empty()
+                                        >
+                                            <summary>"Use whole containers"</summary>
+                                            <div class="card__grid">
+                                                {macro_input(
+                                                    "Servings per container",
+                                                    "e.g. 4",
+                                                    "servings",
+                                                    move || {
+                                                        ingredients.with(|items| {
+                                                            items
+                                                                .iter()
+                                                                .find(|item| item.id == id)
+                                                                .map(|item| item.servings_per_container.clone())
+                                                                .unwrap_or_default()
+                                                        })
+                                                    },
+                                                    move |value| {
+                                                        update_ingredient(set_ingredients, id, |item| item.servings_per_container = value);
+                                                    },
+                                                    read_only.into(),
+                                                    numbers_only.into(),
+                                                )}
+                                                <label class="card__field card__field--check">
+                                                    <input
+                                                        type="checkbox"
+                                                        disabled=move || read_only.get()
+                                                        prop:checked=move || {
+                                                            ingredients.with(|items| {
+                                                                items.iter().any(|item| item.id == id && item.by_container)
+                                                            })
+                                                        }
+                                                        on:change=move |ev| {
+                                                            let checked = event_target_checked(&ev);
+                                                            update_ingredient(set_ingredients, id, |item| item.by_container = checked);
+                                                        }
+                                                    />
+                                                    <span>"Amount used is a number of containers"</span>
+                                                </label>
+                                            </div>
+                                        </details>
+                                    </Show>
+
+                                    <Show when=move || incomplete_ids.with(|ids| ids.contains(&id))>
+                                        <p class="field-warning">
+                                            "Blank macro: counted as unknown. Enter 0 if this food really has none."
+                                        </p>
+                                    </Show>
+
+                                    <Show when=move || exceeds_weight.get()>
+                                        <p class="field-error">
+                                            {move || format!(
+                                                "Macros exceed stated weight: protein, fat and net carbs add up to more than {} g.",
+                                                basis().reference_grams().unwrap_or_default(),
+                                            )}
+                                        </p>
+                                    </Show>
+
+                                    <Show when=move || !read_only.get()>
+                                        <label class="what-if">
+                                            <span>"What-if servings"</span>
+                                            <input
+                                                type="range"
+                                                min="0"
+                                                step="0.25"
+                                                max=move || {
+                                                    ingredients.with(|items| {
+                                                        items
+                                                            .iter()
+                                                            .find(|item| item.id == id)
+                                                            .map(|item| what_if_max(parse_quantity(&item.servings)))
+                                                            .unwrap_or(4.0)
+                                                    })
+                                                }
+                                                prop:value=move || {
+                                                    let servings = ingredients.with(|items| {
+                                                        items
+                                                            .iter()
+                                                            .find(|item| item.id == id)
+                                                            .map(|item| {
+                                                                servings_overrides.with(|overrides| effective_servings(item, overrides))
+                                                            })
+                                                            .unwrap_or_default()
+                                                    });
+                                                    servings.to_string()
+                                                }
+                                                on:input=move |ev| {
+                                                    let value = parse_quantity(&event_target_value(&ev));
+                                                    servings_overrides.update(|overrides| {
+                                                        overrides.insert(id, value);
+                                                    });
+                                                }
+                                                on:change=move |ev| {
+                                                    let value = format_input_value(parse_quantity(&event_target_value(&ev)));
+                                                    update_ingredient(set_ingredients, id, |item| item.servings = value);
+                                                    servings_overrides.update(|overrides| {
+                                                        overrides.remove(&id);
+                                                    });
+                                                }
+                                            />
+                                            <output>
+                                                {move || {
+                                                    servings_overrides
+                                                        .with(|overrides| overrides.get(&id).copied())
+                                                        .map(|value| {
+                                                            format!(
+                                                                "Previewing {} {}",
+                                                                format_number(value, rounding.get()),
+                                                                amount_suffix(),
+                                                            )
+                                                        })
+                                                        .unwrap_or_else(|| "Drag to preview totals".to_string())
+                                                }}
+                                            </output>
+                                        </label>
+                                    </Show>
+
                                     <div class="card__summary">
-                                        <p>{move || format!("Protein: {} g", format_number(per_recipe_protein()))}</p>
-                                        <p>{move || format!("Fat: {} g", format_number(per_recipe_fat()))}</p>
-                                        <p>{move || format!("Net carbs: {} g", format_number(per_recipe_carbs()))}</p>
+                                        <p>
+                                            {move || {
+                                                let calories = total_calories((per_recipe_protein(), per_recipe_fat(), per_recipe_carbs()));
+                                                format!("Calories: {}", format_kcal(calories, rounding.get()))
+                                            }}
+                                            <Show when=move || high_energy_ids.with(|ids| ids.contains(&id))>
+                                                <span
+                                                    class="energy-badge"
+                                                    title=move || format!("More than {}% of the recipe's calories", high_energy_percent.get())
+                                                >
+                                                    "High energy"
+                                                </span>
+                                            </Show>
+                                        </p>
+                                        <p>{move || format!("Protein: {} g", format_number(per_recipe_protein(), rounding.get()))}</p>
+                                        <p>{move || format!("Fat: {} g", format_number(per_recipe_fat(), rounding.get()))}</p>
+                                        <p>{move || format!("Net carbs: {} g", format_number(per_recipe_carbs(), rounding.get()))}</p>
                                         <p>{move || {
                                             let protein = per_recipe_protein();
                                             let fat = per_recipe_fat();
                                             let carbs = per_recipe_carbs();
-                                            format!("P:E ratio: {}", format_ratio((protein, fat, carbs)))
+                                            let kind = ratio_mode.get();
+                                            let energy = with_fiber((protein, fat, carbs), ratio_fiber(per_recipe_fiber()));
+                                            format!("{} ratio: {}", kind.label(), format_ratio_as(kind, energy, rounding.get(), &unbounded_ratio()))
                                         }}</p>
+                                        <div
+                                            class="contribution screen-only"
+                                            title=move || {
+                                                let [protein, fat, carbs] = contribution.get();
+                                                format!(
+                                                    "Share of the recipe: protein {}, fat {}, net carbs {}",
+                                                    format_percent(protein),
+                                                    format_percent(fat),
+                                                    format_percent(carbs),
+                                                )
+                                            }
+                                        >
+                                            <div class="contribution__track">
+                                                <div
+                                                    class="contribution__bar"
+                                                    style:width=move || {
+                                                        format!("{:.1}%", contribution.get()[0].unwrap_or_default().clamp(0.0, 100.0))
+                                                    }
+                                                ></div>
+                                            </div>
+                                            <span class="contribution__label">
+                                                {move || format!("{} of recipe protein", format_percent(contribution.get()[0]))}
+                                            </span>
+                                        </div>
                                     </div>
                                 </article>
                             }
@@ -359,42 +2940,1019 @@ pub fn App() -> impl IntoView {
                 />
             </section>
 
-            <section class="app__summary screen-only">
-                <h2>Totals</h2>
-                <ul>
-                    <li>
-                        <span>Total protein</span>
-                        <strong>{
-                            move || {
-                                let (protein, _, _) = totals.get();
-                                format!("{} g", format_number(protein))
+            <Show when=move || compact_view.get()>
+                <section class="app__table screen-only">
+                    <table class="compare-table compact-table">
+                        <thead>
+                            <tr>
+                                <th>"Food"</th>
+                                <th>"Protein (g)"</th>
+                                <th>"Fat (g)"</th>
+                                <th>"Net carbs (g)"</th>
+                                <th>"Macros"</th>
+                                <th>"Amount"</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            <For
+                                each=move || visible_ingredients.get()
+                                key=|ingredient: &Ingredient| ingredient.id
+                                children=move |ingredient: Ingredient| {
+                                    let id = ingredient.id;
+                                    let cells: [NodeRef<leptos::html::Input>; 5] = std::array::from_fn(|_| NodeRef::new());
+                                    Effect::new(move || {
+                                        if focus_row.get() == Some(id) && let Some(input) = cells[0].get() {
+                                            let _ = input.focus();
+                                            focus_row.set(None);
+                                        }
+                                    });
+                                    let field = move |read: fn(&Ingredient) -> String| {
+                                        ingredients.with(|items| {
+                                            items
+                                                .iter()
+                                                .find(|item| item.id == id)
+                                                .map(read)
+                                                .unwrap_or_default()
+                                        })
+                                    };
+                                    // Enter moves to the next cell, then to the next row; in the
+                                    // last cell of the last row it adds a row to type into.
+                                    let on_keydown = move |ev: web_sys::KeyboardEvent, column: usize| {
+                                        if ev.key() != "Enter" || ev.is_composing() {
+                                            return;
+                                        }
+                                        ev.prevent_default();
+                                        if let Some(next) = cells.get(column + 1) {
+                                            if let Some(input) = next.get_untracked() {
+                                                let _ = input.focus();
+                                            }
+                                            return;
+                                        }
+                                        let next_row = visible_ingredients.with_untracked(|items| {
+                                            let index = items.iter().position(|item| item.id == id)?;
+                                            items.get(index + 1).map(|item| item.id)
+                                        });
+                                        match next_row {
+                                            Some(next) => focus_row.set(Some(next)),
+                                            None if !read_only.get_untracked() => add_row_and_focus(),
+                                            None => {}
+                                        }
+                                    };
+                                    let cell = move |column: usize,
+                                                     label: &'static str,
+                                                     read: fn(&Ingredient) -> String,
+                                                     write: fn(&mut Ingredient, String)| {
+                                        view! {
+                                            <td>
+                                                <input
+                                                    class="text-input"
+                                                    type="text"
+                                                    inputmode=(column > 0).then_some("decimal")
+                                                    aria-label=label
+                                                    node_ref=cells[column]
+                                                    readonly=move || read_only.get()
+                                                    prop:value=move || field(read)
+                                                    on:input=move |ev| {
+                                                        let value = event_target_value(&ev);
+                                                        update_ingredient(set_ingredients, id, |item| write(item, value));
+                                                    }
+                                                    on:keydown=move |ev| on_keydown(ev, column)
+                                                />
+                                            </td>
+                                        }
+                                    };
+                                    view! {
+                                        <tr>
+                                            {cell(0, "Food", |item| item.name.clone(), |item, value| item.name = value)}
+                                            {cell(1, "Protein", |item| item.protein.clone(), |item, value| item.protein = value)}
+                                            {cell(2, "Fat", |item| item.fat.clone(), |item, value| item.fat = value)}
+                                            {cell(3, "Net carbs", |item| item.net_carbs.clone(), |item, value| item.net_carbs = value)}
+                                            <td class="compact-table__unit">{move || field(Ingredient::macro_label)}</td>
+                                            {cell(4, "Amount", |item| item.servings.clone(), |item, value| item.servings = value)}
+                                        </tr>
+                                    }
+                                }
+                            />
+                        </tbody>
+                    </table>
+                    <p class="tool-preview">
+                        "Press Enter to move to the next cell; Enter in the last row's amount adds a row."
+                    </p>
+                </section>
+            </Show>
+
+            {summary}
+
+            <Show when=move || !read_only.get()>
+                <section class="app__tools screen-only">
+                    <h2>"Batch tools"</h2>
+                    <Show when=move || barcode_enabled.get()>
+                        <div class="tool-row">
+                            <button
+                                class="secondary"
+                                disabled=move || scanning.get()
+                                on:click=move |_| {
+                                    scan_status.set(None);
+                                    scanning.set(true);
+                                }
+                            >
+                                "Scan barcode"
+                            </button>
+                            <span class="tool-preview">
+                                "Adds a packaged food using its nutrition label data."
+                            </span>
+                        </div>
+                        <Show when=move || scanning.get()>
+                            <BarcodeScanner
+                                on_code=on_barcode
+                                on_close=Callback::new(move |()| scanning.set(false))
+                            />
+                        </Show>
+                        {move || {
+                            scan_status.get().map(|status| view! { <p class="tool-preview">{status}</p> })
+                        }}
+                    </Show>
+                    <div class="tool-row">
+                        <button class="secondary" on:click=snap_servings>
+                            "Snap servings to ¼"
+                        </button>
+                        <span class="tool-preview">
+                            {move || {
+                                let (snapped, snapped_fiber) = snapped_totals.get();
+                                format!(
+                                    "{} now {} → {} after snapping",
+                                    ratio_mode.get().label(),
+                                    shown_ratio(totals.get(), total_fiber.get()),
+                                    shown_ratio(snapped, snapped_fiber),
+                                )
+                            }}
+                        </span>
+                    </div>
+                    <div class="tool-row">
+                        <input
+                            class="text-input"
+                            type="text"
+                            inputmode="decimal"
+                            placeholder="Servings (or grams) for every food"
+                            prop:value=move || equal_servings.get()
+                            on:input=move |ev| equal_servings.set(event_target_value(&ev))
+                        />
+                        <button
+                            class="secondary"
+                            disabled=move || equal_servings.with(|raw| canonical_quantity(raw).is_empty())
+                            on:click=equalize_servings
+                        >
+                            "Set all servings to this"
+                        </button>
+                        <span class="tool-preview">"Locked foods keep their amounts."</span>
+                    </div>
+                    <div class="tool-row">
+                        <input
+                            class="text-input"
+                            type="text"
+                            inputmode="decimal"
+                            placeholder="Percent, e.g. 7 or -5"
+                            prop:value=move || macro_adjust_percent.get()
+                            on:input=move |ev| macro_adjust_percent.set(event_target_value(&ev))
+                        />
+                        <button
+                            class="secondary"
+                            disabled=move || macro_adjust_factor.get().is_none()
+                            on:click=adjust_all_macros
+                        >
+                            "Adjust all macros by %"
+                        </button>
+                        <span class="tool-preview">
+                            {move || match adjusted_macro_totals.get() {
+                                Some(adjusted) => format!(
+                                    "Label values of unlocked foods ×{}: protein {} g, fat {} g, net carbs {} g; {} {} → {}",
+                                    format_number(macro_adjust_factor.get().unwrap_or(1.0), rounding.get()),
+                                    format_number(adjusted.0, rounding.get()),
+                                    format_number(adjusted.1, rounding.get()),
+                                    format_number(adjusted.2, rounding.get()),
+                                    ratio_mode.get().label(),
+                                    shown_ratio(totals.get(), total_fiber.get()),
+                                    shown_ratio(adjusted, total_fiber.get()),
+                                ),
+                                None => "Scales protein, fat and net carbs, not servings.".to_string(),
+                            }}
+                        </span>
+                    </div>
+                    <div class="tool-row">
+                        <button class="secondary" on:click=auto_fill_servings>
+                            "Auto-fill servings to targets"
+                        </button>
+                        <span class="tool-preview">
+                            "Picks amounts that best match your daily targets below."
+                        </span>
+                    </div>
+                    {move || {
+                        auto_fill_error
+                            .get()
+                            .map(|error| view! { <p class="field-error">{error}</p> })
+                    }}
+                    <div class="tool-row">
+                        <input
+                            class="text-input"
+                            type="text"
+                            inputmode="decimal"
+                            placeholder="Total protein (g)"
+                            prop:value=move || protein_goal.get()
+                            on:input=move |ev| protein_goal.set(event_target_value(&ev))
+                        />
+                        <button
+                            class="secondary"
+                            disabled=move || protein_scale.get().is_none()
+                            on:click=scale_to_protein
+                        >
+                            "Scale unlocked servings to this protein"
+                        </button>
+                        <span class="tool-preview">
+                            {move || {
+                                let (protein, fat, carbs) = totals.get();
+                                let locked = locked_totals.get();
+                                match protein_scale.get() {
+                                    Some(scale) if locked == (0.0, 0.0, 0.0) => format!(
+                                        "×{}: protein {} g, fat {} g, net carbs {} g; {} unchanged",
+                                        format_number(scale, rounding.get()),
+                                        format_number(protein * scale, rounding.get()),
+                                        format_number(fat * scale, rounding.get()),
+                                        format_number(carbs * scale, rounding.get()),
+                                        ratio_mode.get().label(),
+                                    ),
+                                    Some(scale) => {
+                                        let scaled = (
+                                            locked.0 + (protein - locked.0) * scale,
+                                            locked.1 + (fat - locked.1) * scale,
+                                            locked.2 + (carbs - locked.2) * scale,
+                                        );
+                                        let locked_fiber = locked_fiber.get();
+                                        let scaled_fiber = locked_fiber + (total_fiber.get() - locked_fiber) * scale;
+                                        format!(
+                                            "×{} for unlocked foods: protein {} g, fat {} g, net carbs {} g; {} {}",
+                                            format_number(scale, rounding.get()),
+                                            format_number(scaled.0, rounding.get()),
+                                            format_number(scaled.1, rounding.get()),
+                                            format_number(scaled.2, rounding.get()),
+                                            ratio_mode.get().label(),
+                                            shown_ratio(scaled, scaled_fiber),
+                                        )
+                                    }
+                                    None if protein <= f64::MIN_POSITIVE => {
+                                        "Add some protein to the recipe first.".to_string()
+                                    }
+                                    None => "Enter a protein total to preview.".to_string(),
+                                }
+                            }}
+                        </span>
+                    </div>
+                    <div class="tool-row">
+                        <input
+                            class="text-input"
+                            type="text"
+                            inputmode="decimal"
+                            placeholder="Calorie budget (kcal)"
+                            prop:value=move || calorie_goal.get()
+                            on:input=move |ev| {
+                                calorie_goal.set(event_target_value(&ev));
+                                calorie_scale_message.set(None);
                             }
-                        }</strong>
-                    </li>
-                    <li>
-                        <span>Total fat</span>
-                        <strong>{
-                            move || {
-                                let (_, fat, _) = totals.get();
-                                format!("{} g", format_number(fat))
+                        />
+                        <button
+                            class="secondary"
+                            disabled=move || calorie_scale.get().is_none()
+                            on:click=scale_to_calories
+                        >
+                            "Scale unlocked servings to this budget"
+                        </button>
+                        <span class="tool-preview">
+                            {move || {
+                                if let Some(message) = calorie_scale_message.get() {
+                                    return message;
+                                }
+                                let calories = total_calories(totals.get());
+                                match calorie_scale.get() {
+                                    Some(scale) => format!(
+                                        "×{} for unlocked foods; {} unchanged when nothing is locked",
+                                        format_number(scale, rounding.get()),
+                                        ratio_mode.get().label(),
+                                    ),
+                                    None if calories <= f64::MIN_POSITIVE => {
+                                        "Add some food to the recipe first.".to_string()
+                                    }
+                                    None => format!(
+                                        "Currently {}. Enter a budget to preview.",
+                                        format_kcal(calories, rounding.get())
+                                    ),
+                                }
+                            }}
+                        </span>
+                    </div>
+                    <div class="tool-row">
+                        <input
+                            class="text-input"
+                            type="text"
+                            placeholder="Paste a recipe link to add its ingredients"
+                            prop:value=move || import_link.get()
+                            on:input=move |ev| import_link.set(event_target_value(&ev))
+                        />
+                        <button class="secondary" on:click=import_from_link>
+                            "Import from link"
+                        </button>
+                    </div>
+                    {move || {
+                        import_error
+                            .get()
+                            .map(|error| view! { <p class="field-error">{error}</p> })
+                    }}
+                    <div class="tool-row">
+                        <textarea
+                            class="csv-input"
+                            rows="4"
+                            placeholder="Paste a CSV export from MyFitnessPal, Cronometer or FatSecret, or any CSV with Food, Protein (g), Fat (g), Carbohydrates (g) and optionally Fiber (g) columns"
+                            prop:value=move || import_csv.get()
+                            on:input=move |ev| import_csv.set(event_target_value(&ev))
+                        ></textarea>
+                        <button class="secondary" on:click=import_from_csv>
+                            "Import CSV"
+                        </button>
+                    </div>
+                    {move || {
+                        import_csv_error
+                            .get()
+                            .map(|error| view! { <p class="field-error">{error}</p> })
+                    }}
+                    <div class="tool-row">
+                        <textarea
+                            class="csv-input"
+                            rows="4"
+                            placeholder="Paste the text of a Nutrition Facts label (Total Fat, Total Carbohydrate, Dietary Fiber, Protein…)"
+                            prop:value=move || pasted_label.get()
+                            on:input=move |ev| pasted_label.set(event_target_value(&ev))
+                        ></textarea>
+                        <button class="secondary" on:click=import_from_label>
+                            "Add from label"
+                        </button>
+                    </div>
+                    {move || {
+                        label_error
+                            .get()
+                            .map(|error| view! { <p class="field-error">{error}</p> })
+                    }}
+                    <div class="tool-row">
+                        <textarea
+                            class="csv-input"
+                            rows="4"
+                            placeholder="Paste table: name, protein, fat, net carbs, servings (copied from a spreadsheet)"
+                            prop:value=move || pasted_table.get()
+                            on:input=move |ev| pasted_table.set(event_target_value(&ev))
+                        ></textarea>
+                        <div class="tool-stack">
+                            <label class="setting-field">
+                                <input
+                                    type="checkbox"
+                                    prop:checked=move || paste_replaces.get()
+                                    on:change=move |ev| paste_replaces.set(event_target_checked(&ev))
+                                />
+                                <span>"Replace current ingredients"</span>
+                            </label>
+                            <button class="secondary" on:click=import_pasted_table>
+                                "Add pasted rows"
+                            </button>
+                        </div>
+                    </div>
+                    <For
+                        each=move || paste_errors.get()
+                        key=|error: &String| error.clone()
+                        children=|error: String| view! { <p class="field-error">{error}</p> }
+                    />
+                </section>
+            </Show>
+
+            <section class="app__library screen-only">
+                <h2>"Saved recipes"</h2>
+                <div class="filter-row">
+                    <button class="secondary" on:click=save_recipe_to_library>
+                        "Save to library"
+                    </button>
+                    <span class="tool-preview">
+                        "Kept in this browser only. Saving again under the same name replaces it."
+                    </span>
+                </div>
+                <div class="filter-row">
+                    <button
+                        class="secondary"
+                        disabled=move || library.with(Vec::is_empty)
+                        on:click=export_library
+                    >
+                        "Export library"
+                    </button>
+                    <label class="file-button">
+                        "Import library"
+                        <input type="file" accept=".json,application/json" on:change=import_library />
+                    </label>
+                    <label class="setting-field">
+                        <input
+                            type="checkbox"
+                            prop:checked=move || library_replaces.get()
+                            on:change=move |ev| library_replaces.set(event_target_checked(&ev))
+                        />
+                        <span>"Replace the library instead of adding to it"</span>
+                    </label>
+                </div>
+                {move || library_message.get().map(|message| view! { <p class="tool-preview">{message}</p> })}
+                <Show
+                    when=move || library.with(|entries| !entries.is_empty())
+                    fallback=|| view! { <p class="tool-preview">"No saved recipes yet."</p> }
+                >
+                    <ul class="library-list">
+                        <For
+                            each=move || library.get()
+                            key=|saved: &SavedRecipe| {
+                                (saved.name().to_lowercase(), saved.modified_at.to_bits(), saved.favorite)
                             }
-                        }</strong>
-                    </li>
-                    <li>
-                        <span>Total net carbs</span>
-                        <strong>{
+                            children=move |saved: SavedRecipe| {
+                                let name = saved.name().to_string();
+                                let star_name = name.clone();
+                                let delete_name = name.clone();
+                                let recipe = saved.recipe.clone();
+                                view! {
+                                    <li>
+                                        <button
+                                            class="ghost library-list__star"
+                                            class=("library-list__star--on", saved.favorite)
+                                            aria-pressed=saved.favorite.to_string()
+                                            title=if saved.favorite { "Unstar" } else { "Star to list first" }
+                                            on:click=move |_| toggle_favorite(star_name.clone())
+                                        >
+                                            {if saved.favorite { "★" } else { "☆" }}
+                                        </button>
+                                        <span class="library-list__name">{name}</span>
+                                        <span class="tool-preview">
+                                            {humanize_since(js_sys::Date::now(), saved.modified_at)}
+                                        </span>
+                                        <button
+                                            class="secondary"
+                                            on:click=move |_| open_saved_recipe(recipe.clone())
+                                        >
+                                            "Open"
+                                        </button>
+                                        <button
+                                            class="ghost"
+                                            on:click=move |_| delete_saved_recipe(delete_name.clone())
+                                        >
+                                            "Delete"
+                                        </button>
+                                    </li>
+                                }
+                            }
+                        />
+                    </ul>
+                </Show>
+            </section>
+
+            <section class="app__compare screen-only">
+                <h2>"Compare with another recipe"</h2>
+                <div class="filter-row">
+                    <input
+                        class="text-input"
+                        type="text"
+                        placeholder="Paste a recipe link"
+                        prop:value=move || comparison_link.get()
+                        on:input=move |ev| comparison_link.set(event_target_value(&ev))
+                    />
+                    <button class="secondary" on:click=load_comparison>
+                        "Compare"
+                    </button>
+                    <Show when=move || comparison.with(Option::is_some)>
+                        <button class="ghost" on:click=move |_| comparison.set(None)>
+                            "Clear"
+                        </button>
+                    </Show>
+                </div>
+                {move || {
+                    comparison_error
+                        .get()
+                        .map(|error| view! { <p class="field-error">{error}</p> })
+                }}
+                {move || {
+                    let ((other, other_fiber), other_name) = comparison_totals
+                        .get()
+                        .zip(comparison.with(|loaded| loaded.as_ref().map(|(name, _)| name.clone())))?;
+                    let current = totals.get();
+                    let mode = rounding.get();
+                    let current_name = recipe_name.with(|name| {
+                        if name.trim().is_empty() {
+                            "This recipe".to_string()
+                        } else {
+                            name.clone()
+                        }
+                    });
+                    let gram_row = |label: &'static str, mine: f64, theirs: f64| {
+                        view! {
+                            <tr>
+                                <th>{label}</th>
+                                <td>{format!("{} g", format_number(mine, mode))}</td>
+                                <td>{format!("{} g", format_number(theirs, mode))}</td>
+                                <td>{format!("{} g", format_delta(theirs - mine, mode))}</td>
+                            </tr>
+                        }
+                    };
+                    let style = ratio_style();
+                    let current_fiber = total_fiber.get();
+                    let ratio_delta = match (style.value(current, current_fiber), style.value(other, other_fiber)) {
+                        (Some(mine), Some(theirs)) => format_delta(theirs - mine, mode),
+                        _ => "—".to_string(),
+                    };
+                    Some(view! {
+                        <table class="compare-table">
+                            <thead>
+                                <tr>
+                                    <th></th>
+                                    <th>{current_name}</th>
+                                    <th>{other_name}</th>
+                                    <th>"Difference"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {gram_row("Protein", current.0, other.0)}
+                                {gram_row("Fat", current.1, other.1)}
+                                {gram_row("Net carbs", current.2, other.2)}
+                                <tr class="highlight">
+                                    <th>{format!("{} ratio", style.kind.label())}</th>
+                                    <td>{style.format(current, current_fiber, mode)}</td>
+                                    <td>{style.format(other, other_fiber, mode)}</td>
+                                    <td>{ratio_delta}</td>
+                                </tr>
+                            </tbody>
+                        </table>
+                    })
+                }}
+            </section>
+
+            <section class="app__targets screen-only">
+                <h2>"Daily targets"</h2>
+                <p>
+                    "Optional. When set, each total above also shows its share of the daily target. "
+                    "Targets are stored in this browser only."
+                </p>
+                <div class="card__grid">
+                    {macro_input(
+                        "Protein (g per day)",
+                        "e.g. 150",
+                        "g",
+                        move || target_protein.get(),
+                        move |value| target_protein.set(value),
+                        Signal::stored(false),
+                        numbers_only.into(),
+                    )}
+                    {macro_input(
+                        "Fat (g per day)",
+                        "e.g. 150",
+                        "g",
+                        move || target_fat.get(),
+                        move |value| target_fat.set(value),
+                        Signal::stored(false),
+                        numbers_only.into(),
+                    )}
+                    {macro_input(
+                        "Net carbs (g per day)",
+                        "e.g. 150",
+                        "g",
+                        move || target_carbs.get(),
+                        move |value| target_carbs.set(value),
+                        Signal::stored(false),
+                        numbers_only.into(),
+                    )}
+                </div>
+            </section>
+
+            <section class="app__settings screen-only">
+                <h2>"Settings"</h2>
+                <label class="setting-field">
+                    <span>"Rounding of displayed numbers"</span>
+                    <select on:change=move |ev| {
+                        if let Ok(mode) = event_target_value(&ev).parse() {
+                            rounding_setting.set(mode);
+                            local_ui.update(|ui| ui.rounding = Some(mode));
+                        }
+                    }>
+                        {RoundMode::ALL
+                            .into_iter()
+                            .map(|mode| {
+                                view! {
+                                    <option
+                                        value=mode.to_string()
+                                        selected=move || rounding.get() == mode
+                                    >
+                                        {mode.label()}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                <label class="setting-field">
+                    <span>"Ratio shown for ingredients and totals"</span>
+                    <select on:change=move |ev| {
+                        if let Ok(kind) = event_target_value(&ev).parse() {
+                            ratio_setting.set(kind);
+                            local_ui.update(|ui| ui.ratio = Some(kind));
+                        }
+                    }>
+                        {RatioMode::ALL
+                            .into_iter()
+                            .map(|kind| {
+                                view! {
+                                    <option
+                                        value=kind.to_string()
+                                        selected=move || ratio_mode.get() == kind
+                                    >
+                                        {kind.description()}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                <div class="tool-row">
+                    <button
+                        class="secondary"
+                        disabled=move || read_only.get()
+                        on:click=move |_| {
+                            recipe_ui.set(Some(UiPrefs {
+                                rounding: Some(rounding.get_untracked()),
+                                ratio: Some(ratio_mode.get_untracked()),
+                            }));
+                        }
+                    >
+                        "Save display settings with this recipe"
+                    </button>
+                    <Show when=move || recipe_ui.with(Option::is_some) && !read_only.get()>
+                        <button class="ghost" on:click=move |_| recipe_ui.set(None)>
+                            "Forget them"
+                        </button>
+                    </Show>
+                    <span class="tool-preview">
+                        "Shared links then open with this rounding and ratio."
+                    </span>
+                </div>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || fiber_in_ratio.get()
+                        on:change=move |ev| fiber_in_ratio.set(event_target_checked(&ev))
+                    />
+                    <span>
+                        "Count fiber as energy in ratios (uses the fiber entered under “Work out net carbs from a label”; changes the headline P:E)"
+                    </span>
+                </label>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || show_unbounded.get()
+                        on:change=move |ev| show_unbounded.set(event_target_checked(&ev))
+                    />
+                    <span>
+                        "Show protein-only ratios (no fat or carbs) as unbounded instead of “—”, which stays for empty recipes"
+                    </span>
+                </label>
+                <Show when=move || show_unbounded.get()>
+                    <label class="setting-field">
+                        <span>"Symbol for an unbounded ratio"</span>
+                        <input
+                            class="text-input"
+                            type="text"
+                            placeholder="∞"
+                            prop:value=move || unbounded_symbol.get()
+                            on:change=move |ev| unbounded_symbol.set(event_target_value(&ev))
+                        />
+                    </label>
+                </Show>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || auto_link.get()
+                        on:change=move |ev| auto_link.set(event_target_checked(&ev))
+                    />
+                    <span>"Update link automatically (the address bar follows every edit)"</span>
+                </label>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || strict_blanks.get()
+                        on:change=move |ev| strict_blanks.set(event_target_checked(&ev))
+                    />
+                    <span>"Treat blank macros as unknown and mark totals incomplete"</span>
+                </label>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || numbers_only.get()
+                        on:change=move |ev| numbers_only.set(event_target_checked(&ev))
+                    />
+                    <span>"Only allow digits, decimal points and fractions in number fields"</span>
+                </label>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || tab_skips_remove.get()
+                        on:change=move |ev| tab_skips_remove.set(event_target_checked(&ev))
+                    />
+                    <span>"Skip ingredient Remove buttons when moving with Tab"</span>
+                </label>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || high_contrast.get()
+                        on:change=move |ev| high_contrast.set(event_target_checked(&ev))
+                    />
+                    <span>"High-contrast theme"</span>
+                </label>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || barcode_enabled.get()
+                        on:change=move |ev| barcode_enabled.set(event_target_checked(&ev))
+                    />
+                    <span>"Scan barcodes with the camera (asks for camera permission)"</span>
+                </label>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || print_shortcut.get()
+                        on:change=move |ev| print_shortcut.set(event_target_checked(&ev))
+                    />
+                    <span>"Ctrl/⌘+P prints the recipe report"</span>
+                </label>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || print_energy.get()
+                        on:change=move |ev| print_energy.set(event_target_checked(&ev))
+                    />
+                    <span>"Include an energy (kcal) column in the printed report"</span>
+                </label>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || print_hide_unnamed.get()
+                        on:change=move |ev| print_hide_unnamed.set(event_target_checked(&ev))
+                    />
+                    <span>"Leave unnamed ingredients out of the printed table"</span>
+                </label>
+                <label class="setting-field">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || print_bands.get()
+                        on:change=move |ev| print_bands.set(event_target_checked(&ev))
+                    />
+                    <span>"Mark printed P:E ratios with ●/◐/○ bands and a legend"</span>
+                </label>
+                <label class="setting-field">
+                    <span>"Sugar alcohols counted toward net carbs"</span>
+                    <select on:change=move |ev| {
+                        if let Ok(factor) = event_target_value(&ev).parse() {
+                            sugar_alcohol_factor.set(factor);
+                        }
+                    }>
+                        {[(0.0, "None"), (0.5, "Half"), (1.0, "All")]
+                            .into_iter()
+                            .map(|(factor, label)| {
+                                view! {
+                                    <option
+                                        value=factor.to_string()
+                                        selected=move || sugar_alcohol_factor.get() == factor
+                                    >
+                                        {label}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                <label class="setting-field">
+                    <span>"Keto-friendly up to this many g net carbs per portion"</span>
+                    <input
+                        class="number-input"
+                        type="number"
+                        min="0"
+                        step="1"
+                        prop:value=move || keto_max_carbs.get().to_string()
+                        on:change=move |ev| keto_max_carbs.set(parse_quantity(&event_target_value(&ev)))
+                    />
+                </label>
+                <label class="setting-field">
+                    <span>"High-carb above this many g net carbs per portion"</span>
+                    <input
+                        class="number-input"
+                        type="number"
+                        min="0"
+                        step="1"
+                        prop:value=move || high_carb_min.get().to_string()
+                        on:change=move |ev| high_carb_min.set(parse_quantity(&event_target_value(&ev)))
+                    />
+                </label>
+                <label class="setting-field">
+                    <span>"Servings for newly added ingredients"</span>
+                    <input
+                        class="number-input"
+                        type="text"
+                        inputmode="decimal"
+                        prop:value=move || default_servings.get()
+                        on:change=move |ev| {
+                            let value = canonical_quantity(&event_target_value(&ev));
+                            default_servings.set(if value.is_empty() { "1".to_string() } else { value });
+                        }
+                    />
+                </label>
+                <label class="setting-field">
+                    <span>"Flag ingredients above this share of recipe calories (%)"</span>
+                    <input
+                        class="number-input"
+                        type="number"
+                        min="0"
+                        max="100"
+                        step="5"
+                        prop:value=move || high_energy_percent.get().to_string()
+                        on:change=move |ev| {
+                            high_energy_percent.set(parse_quantity(&event_target_value(&ev)).min(100.0));
+                        }
+                    />
+                </label>
+            </section>
+
+            // Only displayed on narrow screens (see styles.css), where the
+            // header buttons are out of thumb reach.
+            <Show when=move || !read_only.get()>
+                <div class="mobile-action-bar screen-only">
+                    <button class="primary" on:click=add_ingredient>
+                        "+ Add food"
+                    </button>
+                    <button class="secondary" on:click=print_recipe>
+                        "Print"
+                    </button>
+                </div>
+            </Show>
+
+            {move || {
+                last_removed
+                    .get()
+                    .map(|(_, ingredient)| {
+                        let name = ingredient.name.trim();
+                        let message = if name.is_empty() {
+                            "Removed an unnamed ingredient".to_string()
+                        } else {
+                            format!("Removed “{name}”")
+                        };
+                        view! {
+                            <div class="undo-toast screen-only" role="status">
+                                <span>{message}</span>
+                                <button class="secondary" on:click=undo_remove>
+                                    "Undo"
+                                </button>
+                            </div>
+                        }
+                    })
+            }}
+
+            <Show when=move || show_help.get()>
+                <div class="shortcut-overlay screen-only" on:click=move |_| show_help.set(false)>
+                    <div
+                        class="shortcut-dialog"
+                        role="dialog"
+                        aria-modal="true"
+                        aria-labelledby="shortcut-title"
+                        on:click=|ev| ev.stop_propagation()
+                    >
+                        <h2 id="shortcut-title">"Keyboard shortcuts"</h2>
+                        <dl>
+                            <dt><kbd>"?"</kbd></dt>
+                            <dd>"Show or hide this list"</dd>
+                            <dt><kbd>"Esc"</kbd></dt>
+                            <dd>"Close this list"</dd>
+                            <dt><kbd>"Ctrl/⌘"</kbd>" + "<kbd>"P"</kbd></dt>
+                            <dd>"Print the recipe report (can be turned off in Settings)"</dd>
+                            <dt><kbd>"↑"</kbd>" / "<kbd>"↓"</kbd></dt>
+                            <dd>"In a number field, add or subtract 1"</dd>
+                            <dt><kbd>"Shift"</kbd>" + "<kbd>"↑"</kbd>" / "<kbd>"↓"</kbd></dt>
+                            <dd>"In a number field, add or subtract 10"</dd>
+                        </dl>
+                        <button class="secondary" on:click=move |_| show_help.set(false)>
+                            "Close"
+                        </button>
+                    </div>
+                </div>
+            </Show>
+
+            {move || {
+                editing
+                    .get()
+                    .map(|id| {
+                        let field = move |
+                            label: &'static str,
+                            placeholder: &'static str,
+                            get: fn(&Ingredient) -> &String,
+                            set: fn(&mut Ingredient, String),
+                        | {
+                            macro_input(
+                                label,
+                                placeholder,
+                                "g",
+                                move || {
+                                    ingredients.with(|items| {
+                                        items
+                                            .iter()
+                                            .find(|item| item.id == id)
+                                            .map(|item| get(item).clone())
+                                            .unwrap_or_default()
+                                    })
+                                },
+                                move |value| {
+                                    update_ingredient(set_ingredients, id, |item| set(item, value));
+                                },
+                                Signal::from(false),
+                                numbers_only.into(),
+                            )
+                        };
+                        let label_text = move |text: fn(&Ingredient) -> String| {
                             move || {
-                                let (_, _, carbs) = totals.get();
-                                format!("{} g", format_number(carbs))
+                                ingredients.with(|items| {
+                                    items
+                                        .iter()
+                                        .find(|item| item.id == id)
+                                        .map(text)
+                                        .unwrap_or_default()
+                                })
                             }
-                        }</strong>
-                    </li>
-                    <li class="highlight">
-                        <span>P:E ratio</span>
-                        <strong>{move || format_ratio(totals.get())}</strong>
-                    </li>
-                </ul>
-            </section>
+                        };
+                        let macro_label = label_text(Ingredient::macro_label);
+                        view! {
+                            <div class="shortcut-overlay screen-only" on:click=move |_| editing.set(None)>
+                                <div
+                                    class="shortcut-dialog edit-dialog"
+                                    role="dialog"
+                                    aria-modal="true"
+                                    aria-labelledby="edit-title"
+                                    on:click=|ev| ev.stop_propagation()
+                                >
+                                    <h2 id="edit-title">"Edit ingredient"</h2>
+                                    <label class="card__field">
+                                        <span>"Name"</span>
+                                        <input
+                                            class="text-input"
+                                            type="text"
+                                            placeholder="Ingredient name"
+                                            autofocus
+                                            prop:value=move || {
+                                                ingredients.with(|items| {
+                                                    items
+                                                        .iter()
+                                                        .find(|item| item.id == id)
+                                                        .map(|item| item.name.clone())
+                                                        .unwrap_or_default()
+                                                })
+                                            }
+                                            on:input=move |ev| {
+                                                let value = event_target_value(&ev);
+                                                update_ingredient(set_ingredients, id, |item| item.name = value);
+                                            }
+                                        />
+                                    </label>
+                                    <p class="edit-dialog__basis">
+                                        {move || format!("Macros {}", macro_label())}
+                                    </p>
+                                    {field("Protein", "e.g. 20", |item| &item.protein, |item, value| item.protein = value)}
+                                    {field("Fat", "e.g. 5", |item| &item.fat, |item, value| item.fat = value)}
+                                    {field("Net carbs", "e.g. 3", |item| &item.net_carbs, |item, value| item.net_carbs = value)}
+                                    {macro_input_with_warning(
+                                        label_text(Ingredient::amount_label),
+                                        "e.g. 1",
+                                        label_text(Ingredient::amount_suffix),
+                                        move || {
+                                            ingredients.with(|items| {
+                                                items
+                                                    .iter()
+                                                    .find(|item| item.id == id)
+                                                    .map(|item| item.servings.clone())
+                                                    .unwrap_or_default()
+                                            })
+                                        },
+                                        move |value| {
+                                            update_ingredient(set_ingredients, id, |item| item.servings = value);
+                                        },
+                                        Signal::from(false),
+                                        numbers_only.into(),
+                                        zero_amount_warning(ingredients, id),
+                                    )}
+                                    <button class="primary" on:click=move |_| editing.set(None)>
+                                        "Done"
+                                    </button>
+                                </div>
+                            </div>
+                        }
+                    })
+            }}
 
             <section class="print-report print-only">
                 <h1>
@@ -411,15 +3969,27 @@ pub fn App() -> impl IntoView {
                     <thead>
                         <tr>
                             <th>Ingredient</th>
-                            <th>Per serving (g)</th>
-                            <th>Servings used</th>
+                            <th>Label values (g)</th>
+                            <th>Amount used</th>
                             <th>In recipe (g)</th>
-                            <th>P:E ratio</th>
+                            <th>{move || format!("{} ratio", ratio_mode.get().label())}</th>
+                            <Show when=move || print_energy.get()>
+                                <th>Energy (kcal)</th>
+                            </Show>
                         </tr>
                     </thead>
                     <tbody>
                         <For
-                            each=move || ingredients.get()
+                            each=move || {
+                                let hide_unnamed = print_hide_unnamed.get();
+                                ingredients.with(|items| {
+                                    items
+                                        .iter()
+                                        .filter(|item| !hide_unnamed || !item.name.trim().is_empty())
+                                        .cloned()
+                                        .collect::<Vec<_>>()
+                                })
+                            }
                             key=|ingredient: &Ingredient| ingredient.id
                             children=move |ingredient: Ingredient| {
                                 let id = ingredient.id;
@@ -439,7 +4009,14 @@ pub fn App() -> impl IntoView {
                                                     per_protein: parse_quantity(&item.protein),
                                                     per_fat: parse_quantity(&item.fat),
                                                     per_carbs: parse_quantity(&item.net_carbs),
+                                                    per_fiber: parse_quantity(&item.fiber),
                                                     servings: parse_quantity(&item.servings),
+                                                    servings_text: item.servings.trim().to_string(),
+                                                    note: item.note.trim().to_string(),
+                                                    basis: item.basis,
+                                                    base_amount: item.base_amount.clone(),
+                                                    unit: item.unit.clone(),
+                                                    per_container: item.per_container(),
                                                 })
                                                 .unwrap_or_default()
                                         })
@@ -448,40 +4025,110 @@ pub fn App() -> impl IntoView {
 
                                 view! {
                                     <tr>
-                                        <td>{move || row_data.get().name.clone()}</td>
+                                        <td>
+                                            {move || row_data.get().name.clone()}
+                                            {move || {
+                                                let note = row_data.get().note;
+                                                (!note.is_empty())
+                                                    .then(|| view! { <span class="print-report__ingredient-note">{note}</span> })
+                                            }}
+                                        </td>
                                         <td>{move || {
                                             let row = row_data.get();
                                             format!(
-                                                "P {} / F {} / C {}",
-                                                format_number(row.per_protein),
-                                                format_number(row.per_fat),
-                                                format_number(row.per_carbs)
+                                                "P {} / F {} / C {} ({})",
+                                                format_number(row.per_protein, rounding.get()),
+                                                format_number(row.per_fat, rounding.get()),
+                                                format_number(row.per_carbs, rounding.get()),
+                                                row.macro_label()
                                             )
                                         }}</td>
-                                        <td>{move || format_number(row_data.get().servings)}</td>
+                                        <td>{move || {
+                                            let row = row_data.get();
+                                            let amount = if is_fraction(&row.servings_text) {
+                                                row.servings_text.clone()
+                                            } else {
+                                                format_number(row.servings, rounding.get())
+                                            };
+                                            format!("{amount}{}", row.amount_unit())
+                                        }}</td>
                                         <td>{move || {
                                             let row = row_data.get();
                                             format!(
                                                 "P {} / F {} / C {}",
-                                                format_number(row.per_protein * row.servings),
-                                                format_number(row.per_fat * row.servings),
-                                                format_number(row.per_carbs * row.servings)
+                                                format_number(row.per_protein * row.multiplier(), rounding.get()),
+                                                format_number(row.per_fat * row.multiplier(), rounding.get()),
+                                                format_number(row.per_carbs * row.multiplier(), rounding.get())
                                             )
                                         }}</td>
                                         <td>{move || {
                                             let row = row_data.get();
-                                            format_ratio((
-                                                row.per_protein * row.servings,
-                                                row.per_fat * row.servings,
-                                                row.per_carbs * row.servings,
-                                            ))
+                                            let in_recipe = (
+                                                row.per_protein * row.multiplier(),
+                                                row.per_fat * row.multiplier(),
+                                                row.per_carbs * row.multiplier(),
+                                            );
+                                            let energy = with_fiber(in_recipe, ratio_fiber(row.per_fiber * row.multiplier()));
+                                            let ratio = format_ratio_as(ratio_mode.get(), energy, rounding.get(), &unbounded_ratio());
+                                            match pe_band(energy) {
+                                                Some(band) if show_bands() => format!("{} {ratio}", band.symbol()),
+                                                _ => ratio,
+                                            }
                                         }}</td>
+                                        <Show when=move || print_energy.get()>
+                                            <td>{move || {
+                                                let row = row_data.get();
+                                                format_number(
+                                                    total_calories((
+                                                        row.per_protein * row.multiplier(),
+                                                        row.per_fat * row.multiplier(),
+                                                        row.per_carbs * row.multiplier(),
+                                                    )),
+                                                    rounding.get(),
+                                                )
+                                            }}</td>
+                                        </Show>
                                     </tr>
                                 }
                             }
                         />
                     </tbody>
                 </table>
+                <Show when=show_bands>
+                    <ul class="print-report__legend">
+                        {PeBand::ALL
+                            .into_iter()
+                            .map(|band| view! { <li>{format!("{} {}", band.symbol(), band.label())}</li> })
+                            .collect_view()}
+                    </ul>
+                </Show>
+                {move || {
+                    let hidden = if print_hide_unnamed.get() {
+                        ingredients.with(|items| items.iter().filter(|item| item.name.trim().is_empty()).count())
+                    } else {
+                        0
+                    };
+                    (hidden > 0)
+                        .then(|| {
+                            let rows = if hidden == 1 {
+                                "1 unnamed ingredient is".to_string()
+                            } else {
+                                format!("{hidden} unnamed ingredients are")
+                            };
+                            view! {
+                                <p class="print-report__note">
+                                    {format!("{rows} not listed above but included in the totals.")}
+                                </p>
+                            }
+                        })
+                }}
+
+                <Show when=move || !notes.with(|text| text.trim().is_empty())>
+                    <section class="print-report__notes">
+                        <h2>"Notes"</h2>
+                        <p>{move || notes.get()}</p>
+                    </section>
+                </Show>
 
                 <div class="print-report__totals">
                     <div>
@@ -489,7 +4136,7 @@ pub fn App() -> impl IntoView {
                         <strong>{
                             move || {
                                 let (protein, _, _) = totals.get();
-                                format!("{} g", format_number(protein))
+                                format!("{} g", format_number(protein, rounding.get()))
                             }
                         }</strong>
                     </div>
@@ -498,51 +4145,265 @@ pub fn App() -> impl IntoView {
                         <strong>{
                             move || {
                                 let (_, fat, _) = totals.get();
-                                format!("{} g", format_number(fat))
+                                format!("{} g", format_number(fat, rounding.get()))
                             }
                         }</strong>
                     </div>
+                    <Show when=move || sat_fat_total.get().is_some()>
+                        <div>
+                            <span>Total saturated fat</span>
+                            <strong>
+                                {move || {
+                                    let sat_fat = sat_fat_total.get().unwrap_or_default();
+                                    format!("{} g", format_number(sat_fat, rounding.get()))
+                                }}
+                            </strong>
+                        </div>
+                    </Show>
                     <div>
                         <span>Total net carbs</span>
                         <strong>{
                             move || {
                                 let (_, _, carbs) = totals.get();
-                                format!("{} g", format_number(carbs))
+                                format!("{} g", format_number(carbs, rounding.get()))
+                            }
+                        }</strong>
+                    </div>
+                    <div>
+                        <span>
+                            {move || {
+                                let note = if fiber_in_ratio.get() { " (fiber counted as energy)" } else { "" };
+                                format!("{} ratio{note}", ratio_mode.get().label())
+                            }}
+                        </span>
+                        <strong>
+                            {move || {
+                                let totals = with_fiber(totals.get(), ratio_fiber(total_fiber.get()));
+                                format_ratio_as(ratio_mode.get(), totals, rounding.get(), &unbounded_ratio())
+                            }}
+                        </strong>
+                    </div>
+                    <div>
+                        <span>Total calories</span>
+                        <strong>{move || format_kcal(total_calories(totals.get()), rounding.get())}</strong>
+                    </div>
+                    <div>
+                        <span>Energy (fat + net carbs)</span>
+                        <strong>{
+                            move || {
+                                let (_, fat, carbs) = totals.get();
+                                format!("{} g", format_number(fat + carbs, rounding.get()))
+                            }
+                        }</strong>
+                    </div>
+                    <div>
+                        <span>Protein share of calories</span>
+                        <strong>{
+                            move || {
+                                protein_calorie_share(totals.get())
+                                    .map(|share| format!("{share:.0}%"))
+                                    .unwrap_or_else(|| "—".to_string())
                             }
                         }</strong>
                     </div>
                     <div>
-                        <span>P:E ratio</span>
-                        <strong>{move || format_ratio(totals.get())}</strong>
+                        <span>Protein density</span>
+                        <strong>{
+                            move || {
+                                let totals = totals.get();
+                                protein_density(totals.0, total_calories(totals))
+                                    .map(|density| format!("{} g / 100 kcal", format_number(density, rounding.get())))
+                                    .unwrap_or_else(|| "—".to_string())
+                            }
+                        }</strong>
+                    </div>
+                    <div class="print-report__wide">
+                        <span>Calories by macro</span>
+                        <strong>{move || calorie_breakdown(totals.get(), rounding.get())}</strong>
                     </div>
                 </div>
+                <p class="print-report__footer">
+                    "Calculated with the P:E Diet Recipe Calculator · pedietcalc.snoyman.com"
+                </p>
             </section>
         </main>
     }
+    .into_any()
+}
+
+fn macro_input<L, S, V, F>(
+    label: L,
+    placeholder: &'static str,
+    suffix: S,
+    value: V,
+    on_change: F,
+    read_only: Signal<bool>,
+    numbers_only: Signal<bool>,
+) -> impl IntoView
+where
+    L: IntoView + 'static,
+    S: IntoView + 'static,
+    V: Fn() -> String + Send + 'static,
+    F: Fn(String) + Clone + Send + 'static,
+{
+    macro_input_with_warning(
+        label,
+        placeholder,
+        suffix,
+        value,
+        on_change,
+        read_only,
+        numbers_only,
+        Signal::from(None),
+    )
+}
+
+/// Warns on an amount field when the card's food would add nothing.
+fn zero_amount_warning(
+    ingredients: ReadSignal<Vec<Ingredient>>,
+    id: usize,
+) -> Signal<Option<&'static str>> {
+    Signal::derive(move || {
+        ingredients
+            .with(|items| {
+                items
+                    .iter()
+                    .any(|item| item.id == id && item.has_zero_amount())
+            })
+            .then_some(
+                "This adds nothing to the recipe while the amount is 0. Did you mean 1 or more?",
+            )
+    })
 }
 
-fn macro_input<V, F>(label: &'static str, value: V, on_change: F) -> impl IntoView
+/// A [`macro_input`] with its own validation: `warning` is shown under the
+/// field while it is `Some`, without blocking the value.
+#[allow(clippy::too_many_arguments)]
+fn macro_input_with_warning<L, S, V, F>(
+    label: L,
+    placeholder: &'static str,
+    suffix: S,
+    value: V,
+    on_change: F,
+    read_only: Signal<bool>,
+    numbers_only: Signal<bool>,
+    warning: Signal<Option<&'static str>>,
+) -> impl IntoView
 where
+    L: IntoView + 'static,
+    S: IntoView + 'static,
     V: Fn() -> String + Send + 'static,
-    F: Fn(String) + Send + 'static,
+    F: Fn(String) + Clone + Send + 'static,
 {
+    let on_blur_change = on_change.clone();
+    let on_key_change = on_change.clone();
+    let input_ref = NodeRef::<leptos::html::Input>::new();
+
     view! {
         <label class="card__field">
             <span>{label}</span>
+            <span class="input-with-suffix">
             <input
                 class="number-input"
                 type="text"
                 inputmode="decimal"
+                node_ref=input_ref
+                placeholder=placeholder
                 prop:value=value
+                readonly=move || read_only.get()
+                aria-invalid=move || warning.get().map(|_| "true")
                 on:input=move |ev| {
-                    let new_value = event_target_value(&ev);
+                    let mut new_value = event_target_value(&ev);
+                    // Leave IME compositions alone until they are committed.
+                    let composing = ev
+                        .dyn_ref::<web_sys::InputEvent>()
+                        .is_some_and(web_sys::InputEvent::is_composing);
+                    if numbers_only.get_untracked() && !composing {
+                        let filtered = filter_numeric_input(&new_value);
+                        if filtered != new_value {
+                            if let Some(input) = input_ref.get_untracked() {
+                                input.set_value(&filtered);
+                            }
+                            caret_to_end(input_ref);
+                            new_value = filtered;
+                        }
+                    }
                     on_change(new_value);
                 }
+                on:keydown=move |ev| {
+                    if read_only.get_untracked() {
+                        return;
+                    }
+                    let direction = match ev.key().as_str() {
+                        "ArrowUp" => 1.0,
+                        "ArrowDown" => -1.0,
+                        _ => return,
+                    };
+                    ev.prevent_default();
+                    let step = if ev.shift_key() { 10.0 } else { 1.0 };
+                    on_key_change(step_quantity(&event_target_value(&ev), direction * step));
+                    caret_to_end(input_ref);
+                }
+                on:blur=move |ev| {
+                    // Switching windows or tabs blurs the input but leaves it
+                    // focused; don't rewrite a value that is still being typed.
+                    if read_only.get_untracked() || has_focus(input_ref) {
+                        return;
+                    }
+                    let raw = event_target_value(&ev);
+                    let normalized = normalize_quantity_input(&raw);
+                    if normalized != raw {
+                        on_blur_change(normalized);
+                    }
+                }
             />
+            <span class="input-suffix">{suffix}</span>
+            </span>
+            {move || warning.get().map(|message| view! { <span class="field-warning" role="status">{message}</span> })}
         </label>
     }
 }
 
+/// Posts the recipe JSON to a link shortener and returns the short URL it
+/// replies with, either as the plain-text body or as `{ "url": "..." }`.
+async fn create_short_link(payload: &RecipePayload, endpoint: &str) -> Result<String, String> {
+    let response = gloo_net::http::Request::post(endpoint)
+        .json(payload)
+        .map_err(|err| format!("Couldn't prepare the request: {err}"))?
+        .send()
+        .await
+        .map_err(|_| "Couldn't reach the link shortener. Check your connection.".to_string())?;
+    if !response.ok() {
+        return Err(format!(
+            "The link shortener returned an error ({}).",
+            response.status()
+        ));
+    }
+    let body = response
+        .text()
+        .await
+        .map_err(|_| "The link shortener sent an unreadable reply.".to_string())?;
+    let url = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|json| json.get("url")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string());
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(url)
+    } else {
+        Err("The link shortener didn't return a link.".to_string())
+    }
+}
+
+fn share_url_status(length: usize) -> &'static str {
+    if length > SHARE_URL_LIMIT {
+        "over"
+    } else if length as f64 >= SHARE_URL_LIMIT as f64 * SHARE_URL_WARN_FRACTION {
+        "warn"
+    } else {
+        "ok"
+    }
+}
+
 fn update_ingredient<F>(set_ingredients: WriteSignal<Vec<Ingredient>>, id: usize, updater: F)
 where
     F: FnOnce(&mut Ingredient),
@@ -554,73 +4415,622 @@ where
     });
 }
 
-fn parse_quantity(raw: &str) -> f64 {
-    sanitize_quantity(raw.trim().parse::<f64>().unwrap_or(0.0))
+fn has_focus(input_ref: NodeRef<leptos::html::Input>) -> bool {
+    let active = window()
+        .and_then(|win| win.document())
+        .and_then(|document| document.active_element());
+    match (input_ref.get_untracked(), active) {
+        (Some(input), Some(active)) => {
+            let input: &web_sys::Element = &input;
+            *input == active
+        }
+        _ => false,
+    }
+}
+
+/// Moves the caret to the end of the input once a programmatic value update
+/// has been rendered, so rewriting the value doesn't leave it mid-number.
+fn caret_to_end(input_ref: NodeRef<leptos::html::Input>) {
+    request_animation_frame(move || {
+        if let Some(input) = input_ref.get_untracked() {
+            let end = input.value().encode_utf16().count() as u32;
+            let _ = input.set_selection_range(end, end);
+        }
+    });
+}
+
+/// The thousands and decimal separators of the browser's locale, read from
+/// how `Intl.NumberFormat` writes a sample number. `None` for locales that
+/// group differently (e.g. "12,34,567.5") or use other digits.
+fn browser_number_format() -> Option<NumberFormat> {
+    let formatter = js_sys::Intl::NumberFormat::new(&js_sys::Array::new(), &js_sys::Object::new());
+    let sample = formatter
+        .format()
+        .call1(&JsValue::NULL, &JsValue::from_f64(1_234_567.5))
+        .ok()?
+        .as_string()?;
+    match sample.chars().collect::<Vec<_>>().as_slice() {
+        [
+            '1',
+            group,
+            '2',
+            '3',
+            '4',
+            again,
+            '5',
+            '6',
+            '7',
+            decimal,
+            '5',
+        ] if group == again => Some(NumberFormat {
+            group: *group,
+            decimal: *decimal,
+        }),
+        _ => None,
+    }
 }
 
-fn sanitize_quantity(value: f64) -> f64 {
-    if value.is_finite() {
-        value.max(0.0)
-    } else {
-        0.0
+fn prefers_reduced_motion() -> bool {
+    window()
+        .and_then(|win| {
+            win.match_media("(prefers-reduced-motion: reduce)")
+                .ok()
+                .flatten()
+        })
+        .is_some_and(|query| query.matches())
+}
+
+/// Whether a change in totals is big enough to animate: at least 10 g and
+/// a fifth of the larger value in some macro, so ordinary typing doesn't.
+fn is_big_jump(from: (f64, f64, f64), to: (f64, f64, f64)) -> bool {
+    [(from.0, to.0), (from.1, to.1), (from.2, to.2)]
+        .into_iter()
+        .any(|(a, b)| (b - a).abs() >= 10.0 && (b - a).abs() >= 0.2 * a.max(b))
+}
+
+/// Moves `shown` from `from` to `to` over [`COUNT_UP_MS`], one animation
+/// frame at a time, stopping early if a newer change bumped `current`.
+fn count_up(
+    from: (f64, f64, f64),
+    to: (f64, f64, f64),
+    started: f64,
+    token: u32,
+    current: StoredValue<u32>,
+    shown: RwSignal<(f64, f64, f64)>,
+) {
+    request_animation_frame(move || {
+        if current.try_get_value() != Some(token) {
+            return;
+        }
+        let progress = ((js_sys::Date::now() - started) / COUNT_UP_MS).min(1.0);
+        let eased = 1.0 - (1.0 - progress).powi(3);
+        let step = |a: f64, b: f64| a + (b - a) * eased;
+        shown.set((step(from.0, to.0), step(from.1, to.1), step(from.2, to.2)));
+        if progress < 1.0 {
+            count_up(from, to, started, token, current, shown);
+        }
+    });
+}
+
+/// Whether the OS asks for more contrast; used until the user picks a theme.
+fn prefers_more_contrast() -> bool {
+    window()
+        .and_then(|win| win.match_media("(prefers-contrast: more)").ok().flatten())
+        .is_some_and(|query| query.matches())
+}
+
+/// Whether an event comes from a field the user types into, where single-key
+/// shortcuts such as "?" and card swipes must not fire.
+fn is_text_entry(ev: &web_sys::Event) -> bool {
+    ev.target()
+        .and_then(|target| target.dyn_into::<web_sys::HtmlElement>().ok())
+        .is_some_and(|element| {
+            matches!(element.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT")
+                || element.is_content_editable()
+        })
+}
+
+/// Offers `text` to the user as a file named `filename`.
+fn download_text(filename: &str, text: &str, mime: &str) {
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime);
+    let parts = js_sys::Array::of1(&JsValue::from_str(text));
+    let Ok(blob) = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options) else {
+        return;
+    };
+    let Ok(url) = web_sys::Url::create_object_url_with_blob(&blob) else {
+        return;
+    };
+    let link = window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.create_element("a").ok())
+        .and_then(|link| link.dyn_into::<web_sys::HtmlAnchorElement>().ok());
+    if let Some(link) = link {
+        link.set_href(&url);
+        link.set_download(filename);
+        link.click();
+    }
+    // Give the browser a moment to start the download before freeing it.
+    set_timeout(
+        move || {
+            let _ = web_sys::Url::revoke_object_url(&url);
+        },
+        Duration::from_secs(1),
+    );
+}
+
+/// Writes `text` to the system clipboard, returning whether it worked.
+async fn copy_to_clipboard(text: &str) -> bool {
+    let Some(win) = window() else {
+        return false;
+    };
+    let promise = win.navigator().clipboard().write_text(text);
+    wasm_bindgen_futures::JsFuture::from(promise).await.is_ok()
+}
+
+fn print_page() {
+    if let Some(win) = window() {
+        let _ = win.print();
     }
 }
 
-fn format_number(value: f64) -> String {
-    if value.abs() < 0.005 {
-        "0.00".to_string()
+fn confirm(message: &str) -> bool {
+    window()
+        .and_then(|win| win.confirm_with_message(message).ok())
+        .unwrap_or(false)
+}
+
+fn query_param(name: &str) -> Option<String> {
+    let search = window()?.location().search().ok()?;
+    search
+        .strip_prefix('?')
+        .unwrap_or(&search)
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+fn remove_query_param(name: &str) {
+    let Some(win) = window() else {
+        return;
+    };
+    let location = win.location();
+    let search = location.search().unwrap_or_default();
+    let remaining = search
+        .strip_prefix('?')
+        .unwrap_or(&search)
+        .split('&')
+        .filter(|pair| !pair.is_empty() && pair.split('=').next() != Some(name))
+        .collect::<Vec<_>>()
+        .join("&");
+    let new_search = if remaining.is_empty() {
+        String::new()
     } else {
-        format!("{value:.2}")
+        format!("?{remaining}")
+    };
+    if new_search == search {
+        return;
+    }
+    if let Ok(history) = win.history() {
+        let _ = history.replace_state_with_url(
+            &JsValue::NULL,
+            "",
+            Some(&format!(
+                "{}{}{}",
+                location.pathname().unwrap_or_default(),
+                new_search,
+                location.hash().unwrap_or_default()
+            )),
+        );
     }
 }
 
-fn format_ratio(totals: (f64, f64, f64)) -> String {
-    let energy = totals.1 + totals.2;
-    if energy <= f64::MIN_POSITIVE {
-        "—".to_string()
+/// The servings field rounded to the nearest quarter serving. Gram-based
+/// amounts are left as entered.
+fn snapped_servings(item: &Ingredient) -> String {
+    if item.basis == Basis::PerServing {
+        format_input_value(snap_to_quarter(parse_quantity(&item.servings)))
     } else {
-        format!("{:.2}", totals.0 / energy)
+        item.servings.clone()
+    }
+}
+
+/// Sums in-recipe macros per ingredient name (case-insensitive), keeping the
+/// first spelling and the order names first appear in. Unnamed cards are
+/// grouped together.
+fn group_by_name(
+    items: &[Ingredient],
+    overrides: &HashMap<usize, f64>,
+) -> Vec<(String, (f64, f64, f64))> {
+    let mut groups: Vec<(String, (f64, f64, f64))> = Vec::new();
+    let mut index_by_key: HashMap<String, usize> = HashMap::new();
+    for item in items {
+        let name = item.name.trim();
+        let key = name.to_lowercase();
+        let (protein, fat, carbs) = recipe_totals(std::slice::from_ref(item), overrides);
+        let index = *index_by_key.entry(key).or_insert_with(|| {
+            let label = if name.is_empty() {
+                "Unnamed ingredients".to_string()
+            } else {
+                name.to_string()
+            };
+            groups.push((label, (0.0, 0.0, 0.0)));
+            groups.len() - 1
+        });
+        let totals = &mut groups[index].1;
+        totals.0 += protein;
+        totals.1 += fat;
+        totals.2 += carbs;
+    }
+    groups
+}
+
+/// Fiber in grams across `items`, honoring any what-if servings overrides.
+fn recipe_fiber(items: &[Ingredient], overrides: &HashMap<usize, f64>) -> f64 {
+    items
+        .iter()
+        .map(|item| parse_quantity(&item.fiber) * effective_multiplier(item, overrides))
+        .sum()
+}
+
+/// Sums protein, fat, and net carbs across `items`, honoring any what-if
+/// servings overrides.
+fn recipe_totals(items: &[Ingredient], overrides: &HashMap<usize, f64>) -> (f64, f64, f64) {
+    let mut total_protein = 0.0;
+    let mut total_fat = 0.0;
+    let mut total_carbs = 0.0;
+    for item in items {
+        let servings = effective_multiplier(item, overrides);
+        total_protein += parse_quantity(&item.protein) * servings;
+        total_fat += parse_quantity(&item.fat) * servings;
+        total_carbs += parse_quantity(&item.net_carbs) * servings;
+    }
+    (total_protein, total_fat, total_carbs)
+}
+
+/// Combines cards whose names match (trimmed, case-insensitive) by summing
+/// their servings into the first one. Groups whose label values or basis
+/// disagree are left untouched and their names returned so the caller can
+/// warn about them. Ids are not reassigned here.
+fn merge_duplicates(ingredients: &mut Vec<Ingredient>) -> Vec<String> {
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        let key = ingredient.name.trim().to_lowercase();
+        if key.is_empty() {
+            continue;
+        }
+        match groups.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, indices)) => indices.push(index),
+            None => groups.push((key, vec![index])),
+        }
+    }
+
+    let mut skipped = Vec::new();
+    let mut remove = HashSet::new();
+    for (_, indices) in groups.iter().filter(|(_, indices)| indices.len() > 1) {
+        let first = &ingredients[indices[0]];
+        // Amounts are only added up when they count the same unit.
+        let label_values = |item: &Ingredient| {
+            (
+                parse_quantity(&item.protein),
+                parse_quantity(&item.fat),
+                parse_quantity(&item.net_carbs),
+                item.basis,
+                custom_base(item.basis, &item.base_amount),
+                item.unit.trim().to_lowercase(),
+                item.per_container(),
+            )
+        };
+        let expected = label_values(first);
+        if indices
+            .iter()
+            .any(|&index| label_values(&ingredients[index]) != expected)
+        {
+            skipped.push(first.name.trim().to_string());
+            continue;
+        }
+        let total_servings = indices
+            .iter()
+            .map(|&index| parse_quantity(&ingredients[index].servings))
+            .sum::<f64>();
+        ingredients[indices[0]].servings = format_input_value(total_servings);
+        remove.extend(indices[1..].iter().copied());
+    }
+
+    let mut index = 0;
+    ingredients.retain(|_| {
+        let keep = !remove.contains(&index);
+        index += 1;
+        keep
+    });
+    skipped
+}
+
+/// Keeps each ingredient's name, basis and amount but clears its macros, so
+/// the same recipe structure can be filled in from a new batch of labels.
+fn blank_macros(ingredients: &[Ingredient]) -> Vec<Ingredient> {
+    ingredients
+        .iter()
+        .map(|item| Ingredient {
+            protein: String::new(),
+            fat: String::new(),
+            net_carbs: String::new(),
+            sat_fat: String::new(),
+            total_carbs: String::new(),
+            fiber: String::new(),
+            sugar_alcohols: String::new(),
+            ..item.clone()
+        })
+        .collect()
+}
+
+/// Issues the id for a new card. Every card added after startup must get its
+/// id here so that the `For` keys over the ingredients stay unique.
+fn fresh_id(next_id: RwSignal<usize>) -> usize {
+    let id = next_id.get_untracked();
+    next_id.set(id + 1);
+    id
+}
+
+fn has_unique_ids(ingredients: &[Ingredient]) -> bool {
+    let mut seen = HashSet::new();
+    ingredients
+        .iter()
+        .all(|ingredient| seen.insert(ingredient.id))
+}
+
+/// Assigns sequential ids starting at zero and returns the next free id.
+fn renumber_ids(ingredients: &mut [Ingredient]) -> usize {
+    for (index, ingredient) in ingredients.iter_mut().enumerate() {
+        ingredient.id = index;
+    }
+    ingredients.len()
+}
+
+fn local_storage() -> Option<Storage> {
+    window()?.local_storage().ok()?
+}
+
+fn load_setting(key: &str) -> Option<String> {
+    local_storage()?
+        .get_item(&format!("{STORAGE_PREFIX}{key}"))
+        .ok()?
+}
+
+/// The saved-recipe library, most recent first.
+fn load_library() -> Vec<SavedRecipe> {
+    let mut library: Vec<SavedRecipe> = load_setting("library")
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default();
+    sort_library(&mut library);
+    library
+}
+
+fn store_library(library: &[SavedRecipe]) {
+    if let Ok(json) = serde_json::to_string(library) {
+        save_setting("library", &json);
+    }
+}
+
+/// Foods saved by `remember_food`, most recent first.
+fn load_recent_foods() -> Vec<IngredientPayload> {
+    load_setting("recent_foods")
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Set when a `localStorage` write fails because the quota is used up, so the
+/// app can show a banner instead of silently losing the change.
+#[derive(Clone, Copy)]
+struct StorageFull(RwSignal<bool>);
+
+fn save_setting(key: &str, value: &str) {
+    let Some(storage) = local_storage() else {
+        return;
+    };
+    if let Err(err) = storage.set_item(&format!("{STORAGE_PREFIX}{key}"), value) {
+        let quota_exceeded = err
+            .dyn_ref::<js_sys::Error>()
+            .is_some_and(|err| err.name() == "QuotaExceededError");
+        if quota_exceeded && let Some(StorageFull(full)) = use_context::<StorageFull>() {
+            full.set(true);
+        }
+    }
+}
+
+/// Approximate bytes held in `localStorage` for this site; browsers store
+/// keys and values as UTF-16.
+fn storage_usage_bytes() -> usize {
+    let Some(storage) = local_storage() else {
+        return 0;
+    };
+    let length = storage.length().unwrap_or(0);
+    (0..length)
+        .filter_map(|index| storage.key(index).ok().flatten())
+        .map(|key| {
+            let value = storage.get_item(&key).ok().flatten().unwrap_or_default();
+            (key.encode_utf16().count() + value.encode_utf16().count()) * 2
+        })
+        .sum()
+}
+
+/// Creates a signal initialized from `localStorage` (falling back to
+/// `default`) that writes every change back.
+fn persisted_signal<T>(key: &'static str, default: T) -> RwSignal<T>
+where
+    T: FromStr + ToString + Clone + Send + Sync + 'static,
+{
+    let initial = load_setting(key)
+        .and_then(|raw| raw.parse().ok())
+        .unwrap_or(default);
+    let signal = RwSignal::new(initial);
+    Effect::new(move || save_setting(key, &signal.get().to_string()));
+    signal
+}
+
+/// Servings used for totals: a what-if slider preview when one is active,
+/// otherwise the committed field value.
+fn effective_servings(item: &Ingredient, overrides: &HashMap<usize, f64>) -> f64 {
+    overrides
+        .get(&item.id)
+        .copied()
+        .unwrap_or_else(|| parse_quantity(&item.servings))
+}
+
+fn effective_multiplier(item: &Ingredient, overrides: &HashMap<usize, f64>) -> f64 {
+    item.multiplier(effective_servings(item, overrides))
+}
+
+/// `raw` multiplied by `factor` and rounded to hundredths. Blank and zero
+/// values are left as typed, so a blank macro stays unknown.
+fn scaled_quantity(raw: &str, factor: f64) -> String {
+    let value = parse_quantity(raw);
+    if value == 0.0 {
+        return raw.to_string();
+    }
+    format_input_value(round_hundredths(value * factor, RoundMode::Nearest))
+}
+
+/// SVG polyline points for `(timestamp, value)` samples in a 100×24 box,
+/// spaced by time and scaled to the values' range.
+fn sparkline_points(samples: &[(f64, f64)]) -> String {
+    let (Some(&(start, _)), Some(&(end, _))) = (samples.first(), samples.last()) else {
+        return String::new();
+    };
+    let (low, high) = samples.iter().fold(
+        (f64::INFINITY, f64::NEG_INFINITY),
+        |(low, high), (_, value)| (low.min(*value), high.max(*value)),
+    );
+    let last = (samples.len() - 1).max(1) as f64;
+    samples
+        .iter()
+        .enumerate()
+        .map(|(index, (time, value))| {
+            let x = if end > start {
+                (time - start) / (end - start) * 100.0
+            } else {
+                index as f64 / last * 100.0
+            };
+            let y = if high > low {
+                23.0 - (value - low) / (high - low) * 22.0
+            } else {
+                12.0
+            };
+            format!("{x:.1},{y:.1}")
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A share such as "42%", or "—" when the recipe has none of that macro.
+fn format_percent(percent: Option<f64>) -> String {
+    percent.map_or_else(|| "—".to_string(), |percent| format!("{percent:.0}%"))
+}
+
+/// Upper bound of the what-if slider, leaving room to at least double the
+/// committed servings.
+fn what_if_max(servings: f64) -> f64 {
+    (servings * 2.0).max(4.0).ceil()
+}
+
+/// Case-insensitive substring match used by the ingredient filter; an empty
+/// query matches everything.
+fn matches_search(name: &str, query: &str) -> bool {
+    let query = query.trim();
+    query.is_empty() || name.to_lowercase().contains(&query.to_lowercase())
+}
+
+/// e.g. "Protein 80 kcal / Fat 72 kcal / Carbs 12 kcal".
+fn calorie_breakdown(totals: (f64, f64, f64), rounding: RoundMode) -> String {
+    let (protein, fat, carbs) = macro_calories(totals);
+    format!(
+        "Protein {} / Fat {} / Carbs {}",
+        format_kcal(protein, rounding),
+        format_kcal(fat, rounding),
+        format_kcal(carbs, rounding)
+    )
+}
+
+fn format_with_target(total: f64, raw_target: &str, rounding: RoundMode) -> String {
+    match percent_of(total, parse_quantity(raw_target)) {
+        Some(percent) => format!(
+            "{} g ({percent:.0}% of daily)",
+            format_number(total, rounding)
+        ),
+        None => format!("{} g", format_number(total, rounding)),
+    }
+}
+
+/// Like [`format_with_target`], but shows the macro's calories. The share of
+/// the daily target is the same either way.
+fn format_kcal_with_target(
+    total: f64,
+    kcal_per_gram: f64,
+    raw_target: &str,
+    rounding: RoundMode,
+) -> String {
+    let kcal = format_kcal(total * kcal_per_gram, rounding);
+    match percent_of(total, parse_quantity(raw_target)) {
+        Some(percent) => format!("{kcal} ({percent:.0}% of daily)"),
+        None => kcal,
     }
 }
 
-fn encode_recipe(ingredients: &[Ingredient], name: &str) -> Option<String> {
+fn recipe_payload(
+    ingredients: &[Ingredient],
+    name: &str,
+    notes: &str,
+    ui: Option<UiPrefs>,
+    locked: bool,
+) -> RecipePayload {
     let trimmed_name = name.trim();
-    let payload = RecipePayload {
+    RecipePayload {
         name: if trimmed_name.is_empty() {
             None
         } else {
             Some(trimmed_name.to_string())
         },
+        notes: if notes.trim().is_empty() {
+            None
+        } else {
+            Some(notes.to_string())
+        },
         ingredients: ingredients
             .iter()
             .map(|ingredient| IngredientPayload {
                 id: ingredient.id,
                 name: ingredient.name.clone(),
-                protein: parse_quantity(&ingredient.protein),
-                fat: parse_quantity(&ingredient.fat),
-                net_carbs: parse_quantity(&ingredient.net_carbs),
-                servings: parse_quantity(&ingredient.servings),
+                protein: canonical_quantity(&ingredient.protein),
+                fat: canonical_quantity(&ingredient.fat),
+                net_carbs: canonical_quantity(&ingredient.net_carbs),
+                sat_fat: canonical_quantity(&ingredient.sat_fat),
+                total_carbs: canonical_quantity(&ingredient.total_carbs),
+                fiber: canonical_quantity(&ingredient.fiber),
+                sugar_alcohols: canonical_quantity(&ingredient.sugar_alcohols),
+                servings: canonical_quantity(&ingredient.servings),
+                base_amount: canonical_quantity(&ingredient.base_amount),
+                unit: ingredient.unit.trim().to_string(),
+                servings_per_container: canonical_quantity(&ingredient.servings_per_container),
+                by_container: ingredient.by_container,
+                basis: ingredient.basis,
+                note: ingredient.note.trim().to_string(),
+                locked: ingredient.locked,
             })
             .collect(),
-    };
-
-    serde_json::to_vec(&payload)
-        .ok()
-        .map(|bytes| URL_SAFE_NO_PAD.encode(bytes))
+        ui,
+        locked,
+    }
 }
 
-fn decode_recipe(encoded: &str) -> Option<RecipePayload> {
-    let raw = URL_SAFE_NO_PAD.decode(encoded.as_bytes()).ok()?;
-    serde_json::from_slice(&raw).ok()
+/// The recipe in the page's `#recipe=` fragment, if there is one, or why it
+/// couldn't be read.
+fn load_payload_from_url() -> Option<Result<RecipePayload, String>> {
+    let hash = window()?.location().hash().ok()?;
+    Some(try_decode_recipe(Fragment::parse(&hash).get("recipe")?))
 }
 
-fn load_recipe_from_url() -> Option<(Vec<Ingredient>, String)> {
-    let window = window()?;
-    let location = window.location();
-    let hash = location.hash().ok()?;
-    let trimmed = hash.strip_prefix('#').unwrap_or(&hash);
-    let encoded = trimmed.strip_prefix("recipe=")?;
-    let payload = decode_recipe(encoded)?;
+fn recipe_from_payload(payload: RecipePayload) -> RecipeState {
     let mut ingredients = payload
         .ingredients
         .into_iter()
@@ -629,8 +5039,26 @@ fn load_recipe_from_url() -> Option<(Vec<Ingredient>, String)> {
     if ingredients.is_empty() {
         ingredients.push(Ingredient::empty(0));
     }
-    let name = payload.name.unwrap_or_default();
-    Some((ingredients, name))
+    // Hand-edited links may repeat ids, which would confuse the card list.
+    if !has_unique_ids(&ingredients) {
+        renumber_ids(&mut ingredients);
+    }
+    RecipeState {
+        ingredients,
+        name: payload.name.unwrap_or_default(),
+        notes: payload.notes.unwrap_or_default(),
+        ui: payload.ui,
+        locked: payload.locked,
+    }
+}
+
+fn next_free_id(ingredients: &[Ingredient]) -> usize {
+    ingredients
+        .iter()
+        .map(|ingredient| ingredient.id)
+        .max()
+        .map(|max_id| max_id + 1)
+        .unwrap_or(1)
 }
 
 impl From<IngredientPayload> for Ingredient {
@@ -638,23 +5066,135 @@ impl From<IngredientPayload> for Ingredient {
         Self {
             id: payload.id,
             name: payload.name,
-            protein: format_input_value(payload.protein),
-            fat: format_input_value(payload.fat),
-            net_carbs: format_input_value(payload.net_carbs),
-            servings: format_input_value(payload.servings),
+            protein: payload.protein,
+            fat: payload.fat,
+            net_carbs: payload.net_carbs,
+            sat_fat: payload.sat_fat,
+            total_carbs: payload.total_carbs,
+            fiber: payload.fiber,
+            sugar_alcohols: payload.sugar_alcohols,
+            servings: payload.servings,
+            base_amount: payload.base_amount,
+            unit: payload.unit,
+            servings_per_container: payload.servings_per_container,
+            by_container: payload.by_container,
+            basis: payload.basis,
+            note: payload.note,
+            locked: payload.locked,
         }
     }
 }
 
-fn format_input_value(value: f64) -> String {
-    if value.abs() < 0.005 {
-        String::new()
-    } else {
-        format!("{value:.2}")
+/// Registers `sw.js` so the app (and any shared link) keeps working offline.
+/// Browsers without service worker support, or pages served from an insecure
+/// origin, simply skip this.
+fn register_service_worker() {
+    let Some(win) = window() else {
+        return;
+    };
+    let navigator = win.navigator();
+    if !js_sys::Reflect::has(&navigator, &JsValue::from_str("serviceWorker")).unwrap_or(false) {
+        return;
     }
+    let on_error = Closure::<dyn FnMut(JsValue)>::new(|err: JsValue| {
+        leptos::logging::warn!("Service worker registration failed: {err:?}");
+    });
+    let _ = navigator
+        .service_worker()
+        .register("./sw.js")
+        .catch(&on_error);
+    on_error.forget();
 }
 
 pub fn main() {
     console_error_panic_hook::set_once();
-    mount_to_body(|| view! { <App /> });
+    register_service_worker();
+    if let Some(format) = browser_number_format() {
+        set_number_format(format);
+    }
+    // index.html holds a static loading shell in `#app`; swap it for the app.
+    let root = window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id(APP_ROOT_ID))
+        .and_then(|element| element.dyn_into::<web_sys::HtmlElement>().ok());
+    match root {
+        Some(root) => {
+            root.set_inner_html("");
+            leptos::mount::mount_to(root, || view! { <App /> }).forget();
+        }
+        None => mount_to_body(|| view! { <App /> }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ingredient(id: usize, name: &str) -> Ingredient {
+        Ingredient {
+            name: name.to_string(),
+            protein: "10".to_string(),
+            ..Ingredient::empty(id)
+        }
+    }
+
+    fn ids(ingredients: &[Ingredient]) -> Vec<usize> {
+        ingredients.iter().map(|ingredient| ingredient.id).collect()
+    }
+
+    #[test]
+    fn fresh_id_never_repeats() {
+        let next_id = RwSignal::new(next_free_id(&[ingredient(4, "a")]));
+        let issued = (0..3).map(|_| fresh_id(next_id)).collect::<Vec<_>>();
+        assert_eq!(issued, vec![5, 6, 7]);
+        assert_eq!(next_id.get_untracked(), 8);
+    }
+
+    #[test]
+    fn loading_a_link_with_repeated_ids_renumbers_them() {
+        let payload: RecipePayload = serde_json::from_str(
+            r#"{"ingredients": [
+                {"id": 2, "name": "a", "protein": "1", "fat": "", "net_carbs": "", "servings": "1"},
+                {"id": 2, "name": "b", "protein": "1", "fat": "", "net_carbs": "", "servings": "1"}
+            ]}"#,
+        )
+        .unwrap();
+        let state = recipe_from_payload(payload);
+        assert!(has_unique_ids(&state.ingredients));
+        assert_eq!(ids(&state.ingredients), vec![0, 1]);
+    }
+
+    #[test]
+    fn loading_a_link_keeps_unique_ids() {
+        let payload = recipe_payload(
+            &[ingredient(7, "a"), ingredient(3, "b")],
+            "",
+            "",
+            None,
+            false,
+        );
+        let state = recipe_from_payload(payload);
+        assert_eq!(ids(&state.ingredients), vec![7, 3]);
+        assert_eq!(next_free_id(&state.ingredients), 8);
+    }
+
+    #[test]
+    fn merging_duplicates_keeps_the_surviving_ids() {
+        let mut items = vec![
+            ingredient(0, "Egg"),
+            ingredient(5, "Bacon"),
+            ingredient(9, "egg"),
+        ];
+        merge_duplicates(&mut items);
+        assert_eq!(ids(&items), vec![0, 5]);
+        assert!(has_unique_ids(&items));
+    }
+
+    #[test]
+    fn renumbering_returns_the_next_free_id() {
+        let mut items = vec![ingredient(8, "a"), ingredient(8, "b"), ingredient(2, "c")];
+        let next = renumber_ids(&mut items);
+        assert_eq!(ids(&items), vec![0, 1, 2]);
+        assert_eq!(next, next_free_id(&items));
+    }
 }
@@ -0,0 +1,59 @@
+// Service worker for offline use of the P:E Diet Recipe Calculator.
+//
+// Trunk fingerprints the wasm/js/css bundles, so rather than precaching a
+// fixed list we cache every same-origin GET as it is fetched. Page
+// navigations go to the network first so new deployments are picked up, and
+// fall back to the cached shell when offline. Recipes live in the URL hash,
+// which is never sent to the server, so one cached shell serves every link.
+
+const CACHE_NAME = "pedietcalc-v1";
+const SHELL_URL = "./";
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(
+    caches.open(CACHE_NAME).then((cache) => cache.add(SHELL_URL)).then(() => self.skipWaiting()),
+  );
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches
+      .keys()
+      .then((keys) => Promise.all(keys.filter((key) => key !== CACHE_NAME).map((key) => caches.delete(key))))
+      .then(() => self.clients.claim()),
+  );
+});
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  if (request.method !== "GET" || new URL(request.url).origin !== self.location.origin) {
+    return;
+  }
+
+  if (request.mode === "navigate") {
+    event.respondWith(
+      fetch(request)
+        .then((response) => {
+          const copy = response.clone();
+          caches.open(CACHE_NAME).then((cache) => cache.put(SHELL_URL, copy));
+          return response;
+        })
+        .catch(() => caches.match(SHELL_URL)),
+    );
+    return;
+  }
+
+  event.respondWith(
+    caches.match(request).then(
+      (cached) =>
+        cached ||
+        fetch(request).then((response) => {
+          if (response.ok) {
+            const copy = response.clone();
+            caches.open(CACHE_NAME).then((cache) => cache.put(request, copy));
+          }
+          return response;
+        }),
+    ),
+  );
+});